@@ -44,6 +44,18 @@ export interface WagerStatus {
   data?: WagerInfo;
 }
 
+/**
+ * Deposit and settlement flags implied by a wager account's `status`
+ */
+export function statusFlags(status: object) {
+  const [stage] = Object.keys(status);
+  return {
+    player1Deposited: ["waitingPlayer2", "active", "settled", "refunded", "cancelledPlayer1Refunded"].includes(stage),
+    player2Deposited: ["waitingPlayer1", "active", "settled", "refunded", "cancelledPlayer2Refunded"].includes(stage),
+    isSettled: ["settled", "refunded", "cancelledPlayer1Refunded", "cancelledUnfunded", "cancelledPlayer2Refunded"].includes(stage),
+  };
+}
+
 export class SliderPvpClient {
   private program: Program<SliderPvp>;
   private provider: AnchorProvider;
@@ -244,12 +256,13 @@ export class SliderPvpClient {
     
     try {
       const wagerAccount = await this.program.account.wager.fetch(wagerPda);
+      const flags = statusFlags(wagerAccount.status);
       
       const now = Math.floor(Date.now() / 1000);
       
       // Calculate time remaining for game (after both players deposit)
       let timeRemaining: number | null = null;
-      if (wagerAccount.player1Deposited && wagerAccount.player2Deposited) {
+      if (flags.player1Deposited && flags.player2Deposited) {
         const startTime = wagerAccount.startTime.toNumber();
         const elapsed = now - startTime;
         timeRemaining = Math.max(0, TIMEOUT_SECONDS - elapsed);
@@ -271,12 +284,12 @@ export class SliderPvpClient {
           arbiter: wagerAccount.arbiter,
           feeRecipient: wagerAccount.feeRecipient,
           wagerAmount: wagerAccount.wagerAmount.toNumber() / LAMPORTS_PER_SOL,
-          player1Deposited: wagerAccount.player1Deposited,
-          player2Deposited: wagerAccount.player2Deposited,
+          player1Deposited: flags.player1Deposited,
+          player2Deposited: flags.player2Deposited,
           creationTime: wagerAccount.creationTime.toNumber(),
           startTime: wagerAccount.startTime.toNumber(),
           winner: wagerAccount.winner,
-          isSettled: wagerAccount.isSettled,
+          isSettled: flags.isSettled,
           initializationCost: initCost / LAMPORTS_PER_SOL,
           netPayout: (totalPool - initCost) / LAMPORTS_PER_SOL,
           timeRemaining,
@@ -304,10 +317,11 @@ export class SliderPvpClient {
       async (accountInfo) => {
         try {
           const wagerData = await this.program.account.wager.fetch(wagerPda);
+          const flags = statusFlags(wagerData.status);
           const now = Math.floor(Date.now() / 1000);
           
           let timeRemaining: number | null = null;
-          if (wagerData.player1Deposited && wagerData.player2Deposited) {
+          if (flags.player1Deposited && flags.player2Deposited) {
             const startTime = wagerData.startTime.toNumber();
             const elapsed = now - startTime;
             timeRemaining = Math.max(0, TIMEOUT_SECONDS - elapsed);
@@ -326,12 +340,12 @@ export class SliderPvpClient {
             arbiter: wagerData.arbiter,
             feeRecipient: wagerData.feeRecipient,
             wagerAmount: wagerData.wagerAmount.toNumber() / LAMPORTS_PER_SOL,
-            player1Deposited: wagerData.player1Deposited,
-            player2Deposited: wagerData.player2Deposited,
+            player1Deposited: flags.player1Deposited,
+            player2Deposited: flags.player2Deposited,
             creationTime: wagerData.creationTime.toNumber(),
             startTime: wagerData.startTime.toNumber(),
             winner: wagerData.winner,
-            isSettled: wagerData.isSettled,
+            isSettled: flags.isSettled,
             initializationCost: initCost / LAMPORTS_PER_SOL,
             netPayout: (totalPool - initCost) / LAMPORTS_PER_SOL,
             timeRemaining,
@@ -1,13 +1,74 @@
 use anchor_lang::prelude::*;
 use anchor_lang::system_program::{transfer, Transfer};
 use anchor_lang::solana_program::sysvar::rent::Rent;
+use anchor_lang::solana_program::sysvar::instructions::{load_current_index_checked, load_instruction_at_checked};
+use anchor_lang::solana_program::ed25519_program;
 
 declare_id!("9EeZ1eFrs8QAop7c6ihE4CiXenjVpGPdmFyv6w3XnmcT");
 
 const TIMEOUT_SECONDS: i64 = 120;
 const DEPOSIT_TIMEOUT_SECONDS: i64 = 30;
+const CLOCK_SKEW_TOLERANCE_SECONDS: i64 = 0;
 const WINNER_PERCENTAGE: u64 = 95;
 const FEE_PERCENTAGE: u64 = 5;
+const BPS_DENOMINATOR: u64 = 10_000;
+/// Ceiling on the platform fee plus a wager's arbiter fee (basis points of the pool)
+const MAX_COMBINED_FEE_BPS: u64 = 1_000;
+/// Most a keeper is tipped for cranking an expired match's refund (`crank_refund`)
+const KEEPER_TIP_LAMPORTS: u64 = 5_000;
+/// Ceiling on a wager's configurable keeper reward (basis points of the refunded pool)
+const MAX_KEEPER_REWARD_BPS: u16 = 100;
+const MAX_PAIR_INITIALIZATIONS_PER_EPOCH: u16 = 16;
+const MAX_PAIR_INIT_COOLDOWN_SECONDS: i64 = 86_400;
+const MAX_ARBITER_ROTATION: usize = 4;
+/// Times `update_arbiter` may replace a wager's arbiter, so nobody can stall a match by
+/// swapping arbiters indefinitely
+const MAX_ARBITER_CHANGES: u8 = 3;
+const MAX_START_DELAY_SECONDS: i64 = 60;
+/// Time after creation (or the scheduled start, if later) from which an unsettled wager
+/// can be voided with `void_expired`, whatever its deposit state
+const WAGER_EXPIRY_SECONDS: i64 = 60 * 60;
+/// Time after a one-sided cancellation during which the missing player can `reopen_wager`
+const REOPEN_WINDOW_SECONDS: i64 = 5 * 60;
+/// Time after the second deposit for both players of a `require_ready` wager to ready up,
+/// after which `ready_up` readies them automatically
+const READY_TIMEOUT_SECONDS: i64 = 30;
+/// Team members `claim_as_team` can split held winnings between
+const MAX_TEAM_MEMBERS: usize = 8;
+/// Matches `declare_winners_batch` settles in one transaction
+const MAX_BATCH_SETTLEMENTS: usize = 4;
+/// Open wagers a `PlayerActivity` account can track (upper bound for its configurable cap)
+const MAX_ACTIVE_GAMES: usize = 8;
+/// Bytes signed by the arbiter for `declare_winner_signed`: wager key, winner, nonce (LE)
+const SIGNED_RESULT_LEN: usize = 32 + 1 + 8;
+/// Current `Wager` layout. v1 accounts predate the `version` field and have no version
+/// byte; `migrate_wager` grows them and they read as version 0 until rewritten.
+const WAGER_VERSION: u8 = 2;
+
+// Packed status byte layout (see `Wager::status_byte`)
+pub const STATUS_PLAYER1_DEPOSITED: u8 = 1 << 0;
+pub const STATUS_PLAYER2_DEPOSITED: u8 = 1 << 1;
+pub const STATUS_SETTLED: u8 = 1 << 2;
+pub const STATUS_WINNER_SHIFT: u8 = 3; // bits 3-4: 0 = none, 1 = player 1, 2 = player 2
+
+// Action bitmask returned by `query_actions` (see `Wager::available_actions`)
+pub const ACTION_DEPOSIT_PLAYER1: u16 = 1 << 0;
+pub const ACTION_DEPOSIT_PLAYER2: u16 = 1 << 1;
+pub const ACTION_DECLARE_WINNER: u16 = 1 << 2;
+pub const ACTION_REVEAL_RESULT: u16 = 1 << 3;
+pub const ACTION_REFUND: u16 = 1 << 4;
+pub const ACTION_CANCEL_WAGER: u16 = 1 << 5;
+pub const ACTION_ARBITER_VOID: u16 = 1 << 6;
+pub const ACTION_CLOSE_WAGER: u16 = 1 << 7;
+pub const ACTION_RECLAIM_ABANDONED: u16 = 1 << 8;
+pub const ACTION_CLAIM_WINNINGS: u16 = 1 << 9;
+pub const ACTION_SWEEP_UNCLAIMED: u16 = 1 << 10;
+pub const ACTION_REMATCH: u16 = 1 << 11;
+pub const ACTION_VOID_EXPIRED: u16 = 1 << 12;
+pub const ACTION_JOIN_WAGER: u16 = 1 << 13;
+pub const ACTION_MUTUAL_VOID: u16 = 1 << 14;
+
+const _: () = assert!(WINNER_PERCENTAGE + FEE_PERCENTAGE == 100);
 
 #[program]
 pub mod slider_pvp {
@@ -21,39 +82,219 @@ pub mod slider_pvp {
         arbiter: Pubkey,
         fee_recipient: Pubkey,
         wager_amount: u64,
+        options: WagerOptions,
+    ) -> Result<()> {
+        let accounts = ctx.accounts;
+        init_wager(
+            &mut accounts.wager,
+            &accounts.vault,
+            &mut accounts.pair_stats,
+            accounts.payer.key(),
+            &ctx.bumps,
+            player1,
+            player2,
+            player2,
+            arbiter,
+            fee_recipient,
+            wager_amount,
+            options,
+        )
+    }
+
+    /// Open challenge: initialize a wager with only player 1 fixed. Player 2 is left as
+    /// `Pubkey::default()` until someone takes the seat with `join_wager`, so the PDAs
+    /// are derived from `nonce` (see `open_wager_seed`) instead of player 2's key.
+    /// Otherwise identical to `initialize_wager`.
+    pub fn initialize_open_wager(
+        ctx: Context<InitializeOpenWager>,
+        player1: Pubkey,
+        nonce: u64,
+        arbiter: Pubkey,
+        fee_recipient: Pubkey,
+        wager_amount: u64,
+        options: WagerOptions,
+    ) -> Result<()> {
+        let bumps = InitializeWagerBumps {
+            wager: ctx.bumps.wager,
+            vault: ctx.bumps.vault,
+            pair_stats: ctx.bumps.pair_stats,
+        };
+        let accounts = ctx.accounts;
+        init_wager(
+            &mut accounts.wager,
+            &accounts.vault,
+            &mut accounts.pair_stats,
+            accounts.payer.key(),
+            &bumps,
+            player1,
+            Pubkey::default(),
+            open_wager_seed(nonce),
+            arbiter,
+            fee_recipient,
+            wager_amount,
+            options,
+        )?;
+        
+        msg!("Open challenge: nonce {}", nonce);
+        
+        Ok(())
+    }
+
+    /// House wager: `house` takes player 2's seat and is also the fee recipient, staking
+    /// `wager_amount` against player 1. The house funds its stake first with
+    /// `deposit_house`; player 1's deposit then starts the match, which settles, refunds
+    /// and cancels like any other wager.
+    pub fn initialize_house_wager(
+        ctx: Context<InitializeHouseWager>,
+        player1: Pubkey,
+        house: Pubkey,
+        arbiter: Pubkey,
+        wager_amount: u64,
+        options: WagerOptions,
     ) -> Result<()> {
+        let bumps = InitializeWagerBumps {
+            wager: ctx.bumps.wager,
+            vault: ctx.bumps.vault,
+            pair_stats: ctx.bumps.pair_stats,
+        };
+        let accounts = ctx.accounts;
+        init_wager(
+            &mut accounts.wager,
+            &accounts.vault,
+            &mut accounts.pair_stats,
+            accounts.payer.key(),
+            &bumps,
+            player1,
+            house,
+            house,
+            arbiter,
+            house,
+            wager_amount,
+            options,
+        )?;
+        accounts.wager.house_wager = true;
+        
+        msg!("House wager: {} stakes against player 1", house);
+        
+        Ok(())
+    }
+
+    /// The house pre-funds its counter-stake into the vault of a house wager. Same checks
+    /// and effects as `deposit_player2`, with the house signing as player 2.
+    pub fn deposit_house(ctx: Context<DepositPlayer2>) -> Result<()> {
+        require!(ctx.accounts.wager.house_wager, ErrorCode::NotHouseWager);
+        deposit_player2(ctx)
+    }
+
+    /// Take player 2's seat of an open challenge and deposit the stake in one step; the
+    /// first caller wins the seat. Same checks and effects as `deposit_player2` once seated.
+    pub fn join_wager(ctx: Context<DepositPlayer2>) -> Result<()> {
         let wager = &mut ctx.accounts.wager;
         
-        require!(player1 != player2, ErrorCode::SamePlayer);
-        require!(wager_amount > 0, ErrorCode::InvalidWagerAmount);
+        require!(!wager.is_settled(), ErrorCode::WagerAlreadySettled);
+        require!(wager.player2 == Pubkey::default(), ErrorCode::WagerAlreadyJoined);
+        let joiner = ctx.accounts.player2.key();
+        require!(wager.player_number(&joiner).is_none(), ErrorCode::SamePlayer);
         
-        // Calculate total initialization cost (rent for wager + vault PDAs)
-        let rent = Rent::get()?;
-        let wager_rent = rent.minimum_balance(8 + std::mem::size_of::<Wager>());
-        let vault_rent = rent.minimum_balance(0); // Vault has no data
-        let total_initialization_cost = wager_rent.checked_add(vault_rent).unwrap();
-        
-        wager.player1 = player1;
-        wager.player2 = player2;
-        wager.arbiter = arbiter;
-        wager.fee_recipient = fee_recipient;
-        wager.wager_amount = wager_amount;
-        wager.player1_deposited = false;
-        wager.player2_deposited = false;
-        wager.creation_time = Clock::get()?.unix_timestamp;
-        wager.start_time = 0;
-        wager.winner = None;
-        wager.is_settled = false;
-        wager.bump = ctx.bumps.wager;
-        wager.vault_bump = ctx.bumps.vault;
-        wager.initialization_cost = total_initialization_cost;
+        wager.player2 = joiner;
+        require_fee_recipient_not_player(wager)?;
+        msg!("Player 2 joined: {}", joiner);
+        
+        deposit_player2(ctx)
+    }
+
+    /// Create the stats account that counts an arbiter's settled matches (used for fee waivers)
+    pub fn initialize_arbiter_stats(ctx: Context<InitializeArbiterStats>, arbiter: Pubkey) -> Result<()> {
+        let arbiter_stats = &mut ctx.accounts.arbiter_stats;
+        
+        arbiter_stats.arbiter = arbiter;
+        arbiter_stats.settled_count = 0;
+        arbiter_stats.bump = ctx.bumps.arbiter_stats;
+        
+        msg!("Arbiter stats initialized for {}", arbiter);
+        
+        Ok(())
+    }
+
+    /// Create the game counter (`["player_stats", player]`) that new-player fee waivers
+    /// are based on. Anyone may pay for it.
+    pub fn initialize_player_stats(ctx: Context<InitializePlayerStats>, player: Pubkey) -> Result<()> {
+        let player_stats = &mut ctx.accounts.player_stats;
+        
+        player_stats.player = player;
+        player_stats.games_played = 0;
+        player_stats.bump = ctx.bumps.player_stats;
+        
+        msg!("Player stats initialized for {}", player);
+        
+        Ok(())
+    }
+
+    /// Create an insurance pool (`["insurance", authority]`) governed by the signing authority
+    pub fn initialize_insurance_pool(ctx: Context<InitializeInsurancePool>) -> Result<()> {
+        let insurance_pool = &mut ctx.accounts.insurance_pool;
+        
+        insurance_pool.authority = ctx.accounts.authority.key();
+        insurance_pool.total_accrued = 0;
+        insurance_pool.total_paid = 0;
+        insurance_pool.bump = ctx.bumps.insurance_pool;
+        
+        msg!("Insurance pool initialized for {}", insurance_pool.authority);
+        
+        Ok(())
+    }
+
+    /// Pool authority pays a claim out of the insurance pool, keeping the pool rent exempt
+    pub fn insurance_payout(ctx: Context<InsurancePayout>, amount: u64) -> Result<()> {
+        let pool_info = ctx.accounts.insurance_pool.to_account_info();
+        let rent_reserve = Rent::get()?.minimum_balance(8 + InsurancePool::INIT_SPACE);
+        let available = pool_info.lamports().saturating_sub(rent_reserve);
+        require!(amount <= available, ErrorCode::InsufficientInsuranceFunds);
+        require!(!is_reserved_address(ctx.accounts.recipient.key), ErrorCode::ReservedPayoutAddress);
+        
+        let insurance_pool = &mut ctx.accounts.insurance_pool;
+        insurance_pool.total_paid = insurance_pool.total_paid.checked_add(amount).unwrap();
+        
+        // The pool carries data and is owned by this program, so it is debited directly
+        // rather than through a System Program transfer
+        **pool_info.try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.recipient.try_borrow_mut_lamports()? += amount;
+        
+        msg!("Insurance payout: {} lamports to {}", amount, ctx.accounts.recipient.key());
+        
+        Ok(())
+    }
+
+    /// Create a fee vault (`["fee_vault", authority]`) accumulating the fees of every wager
+    /// that names it as fee recipient, withdrawable by the signing authority
+    pub fn initialize_fee_vault(ctx: Context<InitializeFeeVault>) -> Result<()> {
+        let fee_vault = &mut ctx.accounts.fee_vault;
+        
+        fee_vault.authority = ctx.accounts.authority.key();
+        fee_vault.total_withdrawn = 0;
+        fee_vault.bump = ctx.bumps.fee_vault;
         
-        msg!("Wager initialized: {} SOL per player", wager_amount as f64 / 1_000_000_000.0);
-        msg!("Initialization cost: {} SOL (will be deducted from final payout)", total_initialization_cost as f64 / 1_000_000_000.0);
-        msg!("Player 1: {}", player1);
-        msg!("Player 2: {}", player2);
-        msg!("Arbiter: {}", arbiter);
-        msg!("Fee Recipient: {}", fee_recipient);
+        msg!("Fee vault initialized for {}", fee_vault.authority);
+        
+        Ok(())
+    }
+
+    /// Fee vault authority withdraws accumulated fees, keeping the fee vault rent exempt
+    pub fn withdraw_fees(ctx: Context<WithdrawFees>, amount: u64) -> Result<()> {
+        let vault_info = ctx.accounts.fee_vault.to_account_info();
+        let rent_reserve = Rent::get()?.minimum_balance(8 + FeeVault::INIT_SPACE);
+        let available = vault_info.lamports().saturating_sub(rent_reserve);
+        require!(amount <= available, ErrorCode::InsufficientFeeVaultFunds);
+        require!(!is_reserved_address(ctx.accounts.recipient.key), ErrorCode::ReservedPayoutAddress);
+        
+        let fee_vault = &mut ctx.accounts.fee_vault;
+        fee_vault.total_withdrawn = fee_vault.total_withdrawn.checked_add(amount).unwrap();
+        
+        // Like the insurance pool, the fee vault is program owned and debited directly
+        **vault_info.try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.recipient.try_borrow_mut_lamports()? += amount;
+        
+        msg!("Fees withdrawn: {} lamports to {}", amount, ctx.accounts.recipient.key());
         
         Ok(())
     }
@@ -62,12 +303,17 @@ pub mod slider_pvp {
     pub fn deposit_player1(ctx: Context<DepositPlayer1>) -> Result<()> {
         let wager = &ctx.accounts.wager;
         
-        require!(!wager.is_settled, ErrorCode::WagerAlreadySettled);
-        require!(!wager.player1_deposited, ErrorCode::AlreadyDeposited);
-        require!(
-            ctx.accounts.player1.key() == wager.player1,
-            ErrorCode::UnauthorizedPlayer
-        );
+        require!(!wager.is_settled(), ErrorCode::WagerAlreadySettled);
+        // Defense in depth: once the match timer is set no seat can be (re)filled
+        require!(wager.start_time == 0, ErrorCode::MatchAlreadyStarted);
+        require!(!wager.player1_deposited(), ErrorCode::AlreadyDeposited);
+        // A player only stakes against a house that has already funded its side
+        require!(!wager.house_wager || wager.player2_deposited(), ErrorCode::HouseStakeMissing);
+        // One key may never fill both seats, even if the roles were mis-set
+        require!(wager.player1 != wager.player2, ErrorCode::SamePlayer);
+        let seat = wager.player_number(&ctx.accounts.player1.key());
+        require!(seat != Some(2), ErrorCode::SamePlayer);
+        require!(seat == Some(1), ErrorCode::UnauthorizedPlayer);
         
         // Transfer SOL from player1 to vault PDA (not wager PDA)
         let wager_amount = wager.wager_amount;
@@ -78,18 +324,27 @@ pub mod slider_pvp {
                 to: ctx.accounts.vault.to_account_info(),
             },
         );
+        let vault_balance_before = ctx.accounts.vault.lamports();
         transfer(cpi_context, wager_amount)?;
+        require_vault_credited(&ctx.accounts.vault, vault_balance_before, wager_amount)?;
         
-        let wager = &mut ctx.accounts.wager;
+        record_active_game(ctx.accounts.player_activity.as_mut(), &ctx.accounts.wager)?;
         
-        wager.player1_deposited = true;
+        let current_time = Clock::get()?.unix_timestamp;
+        let wager = &mut ctx.accounts.wager;
         
-        // If both players have deposited, start the timer
-        if wager.player2_deposited {
-            wager.start_time = Clock::get()?.unix_timestamp;
-            msg!("Both players deposited! Timer started: {} seconds", TIMEOUT_SECONDS);
+        // If both players have deposited, start the timer (no earlier than the scheduled start).
+        // start_time is set exactly once, by whichever deposit completes the pair, unless
+        // the wager waits for both players to ready up first.
+        if wager.player2_deposited() {
+            wager.deposits_completed_time = current_time;
+            set_status(wager, WagerStatus::Active)?;
+            start_match_when_ready(wager, current_time);
         } else {
-            msg!("Player 1 deposited {} SOL", wager.wager_amount as f64 / 1_000_000_000.0);
+            // First deposit opens the window for the opponent to match it
+            wager.first_deposit_time = current_time;
+            set_status(wager, WagerStatus::WaitingPlayer2)?;
+            msg!("Player 1 deposited {} lamports", wager.wager_amount);
         }
         
         Ok(())
@@ -99,12 +354,15 @@ pub mod slider_pvp {
     pub fn deposit_player2(ctx: Context<DepositPlayer2>) -> Result<()> {
         let wager = &ctx.accounts.wager;
         
-        require!(!wager.is_settled, ErrorCode::WagerAlreadySettled);
-        require!(!wager.player2_deposited, ErrorCode::AlreadyDeposited);
-        require!(
-            ctx.accounts.player2.key() == wager.player2,
-            ErrorCode::UnauthorizedPlayer
-        );
+        require!(!wager.is_settled(), ErrorCode::WagerAlreadySettled);
+        // Defense in depth: once the match timer is set no seat can be (re)filled
+        require!(wager.start_time == 0, ErrorCode::MatchAlreadyStarted);
+        require!(!wager.player2_deposited(), ErrorCode::AlreadyDeposited);
+        // One key may never fill both seats, even if the roles were mis-set
+        require!(wager.player1 != wager.player2, ErrorCode::SamePlayer);
+        let seat = wager.player_number(&ctx.accounts.player2.key());
+        require!(seat != Some(1), ErrorCode::SamePlayer);
+        require!(seat == Some(2), ErrorCode::UnauthorizedPlayer);
         
         // Transfer SOL from player2 to vault PDA (not wager PDA)
         let wager_amount = wager.wager_amount;
@@ -115,323 +373,4799 @@ pub mod slider_pvp {
                 to: ctx.accounts.vault.to_account_info(),
             },
         );
+        let vault_balance_before = ctx.accounts.vault.lamports();
         transfer(cpi_context, wager_amount)?;
+        require_vault_credited(&ctx.accounts.vault, vault_balance_before, wager_amount)?;
         
+        record_active_game(ctx.accounts.player_activity.as_mut(), &ctx.accounts.wager)?;
+        
+        let current_time = Clock::get()?.unix_timestamp;
         let wager = &mut ctx.accounts.wager;
         
-        wager.player2_deposited = true;
+        // If both players have deposited, start the timer (no earlier than the scheduled start).
+        // start_time is set exactly once, by whichever deposit completes the pair, unless
+        // the wager waits for both players to ready up first.
+        if wager.player1_deposited() {
+            wager.deposits_completed_time = current_time;
+            set_status(wager, WagerStatus::Active)?;
+            start_match_when_ready(wager, current_time);
+        } else {
+            // First deposit opens the window for the opponent to match it
+            wager.first_deposit_time = current_time;
+            set_status(wager, WagerStatus::WaitingPlayer1)?;
+            msg!("Player 2 deposited {} lamports", wager.wager_amount);
+        }
+        
+        Ok(())
+    }
+
+    /// Arbiter declares a winner (must be within timeout period). Returns the amounts paid
+    /// as return data, for CPI callers and clients that would otherwise parse the logs.
+    pub fn declare_winner(ctx: Context<DeclareWinner>, winner: u8) -> Result<SettlementResult> {
+        // A committed result can only be settled through `reveal_result`
+        require!(
+            ctx.accounts.wager.result_commitment.is_none(),
+            ErrorCode::ResultAlreadyCommitted
+        );
+        let current_time = Clock::get()?.unix_timestamp;
+        settle_winner(ctx.accounts, winner, current_time)
+    }
+
+    /// Arbiter reports the final score instead of a winner. The higher score wins and is
+    /// paid out exactly as by `declare_winner`; equal scores are a draw, refunding both
+    /// players on the same terms as `arbiter_void` (`player1` and `player2` are then
+    /// required). Either way the scores are kept on the wager.
+    pub fn declare_result(ctx: Context<DeclareWinner>, score1: u32, score2: u32) -> Result<()> {
+        // A committed result can only be settled through `reveal_result`
+        require!(
+            ctx.accounts.wager.result_commitment.is_none(),
+            ErrorCode::ResultAlreadyCommitted
+        );
+        let current_time = Clock::get()?.unix_timestamp;
+        let accounts = ctx.accounts;
+        accounts.wager.scores = Some([score1, score2]);
         
-        // If both players have deposited, start the timer
-        if wager.player1_deposited {
-            wager.start_time = Clock::get()?.unix_timestamp;
-            msg!("Both players deposited! Timer started: {} seconds", TIMEOUT_SECONDS);
+        if score1 > score2 {
+            settle_winner(accounts, 1, current_time)?;
+        } else if score2 > score1 {
+            settle_winner(accounts, 2, current_time)?;
         } else {
-            msg!("Player 2 deposited {} SOL", wager.wager_amount as f64 / 1_000_000_000.0);
+            settle_draw(accounts, current_time)?;
+        }
+        
+        msg!("Result declared: {} - {}", score1, score2);
+        Ok(())
+    }
+
+    /// Arbiter settles up to `MAX_BATCH_SETTLEMENTS` matches in one transaction, e.g. a
+    /// round of a tournament bracket: `remaining_accounts` holds one writable (wager,
+    /// vault, winner_account) triple per entry of `winners`, and every wager must share
+    /// the arbiter and `fee_recipient`. Each match is validated and paid out as by
+    /// `declare_winner`. Wagers whose settlement needs more than that (see
+    /// `Wager::is_batchable`) are rejected, and no receipts are written.
+    pub fn declare_winners_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, DeclareWinnersBatch<'info>>,
+        winners: Vec<u8>,
+    ) -> Result<()> {
+        require!(
+            !winners.is_empty() && winners.len() <= MAX_BATCH_SETTLEMENTS,
+            ErrorCode::InvalidBatch
+        );
+        require!(
+            ctx.remaining_accounts.len() == winners.len() * 3,
+            ErrorCode::InvalidBatch
+        );
+        let current_time = Clock::get()?.unix_timestamp;
+        let arbiter = ctx.accounts.arbiter.key();
+        
+        for (accounts, &winner) in ctx.remaining_accounts.chunks_exact(3).zip(&winners) {
+            let [wager_info, vault, winner_account] = accounts else {
+                return err!(ErrorCode::InvalidBatch);
+            };
+            require!(
+                wager_info.is_writable && vault.is_writable && winner_account.is_writable,
+                ErrorCode::InvalidBatch
+            );
+            // Deserializing checks the owner and discriminator, so only genuine wagers
+            // pass; with no `seeds` constraint here, the vault is checked by hand
+            let mut wager = Account::<Wager>::try_from(wager_info)?;
+            require!(vault.key() == wager.vault_address()?, ErrorCode::InvalidVaultBump);
+            require!(wager.result_commitment.is_none(), ErrorCode::ResultAlreadyCommitted);
+            require!(wager.is_batchable(), ErrorCode::WagerNotBatchable);
+            require!(
+                wager.fee_recipient == ctx.accounts.fee_recipient.key(),
+                ErrorCode::InvalidFeeRecipient
+            );
+            require_arbiter_can_declare(&wager, arbiter, winner, current_time, true)?;
+            
+            let result = pay_out_winner(
+                &mut wager,
+                vault,
+                winner_account,
+                &ctx.accounts.fee_recipient,
+                None,
+                None,
+                Some(ctx.accounts.arbiter.as_ref()),
+                &ctx.accounts.system_program,
+                winner,
+                current_time,
+                0,
+            )?;
+            // Written back right away, so the same wager repeated later in the batch
+            // fails the settled guard
+            wager.exit(&crate::ID)?;
+            
+            msg!("Batch: wager {} won by player {} for {} lamports", wager.key(), winner, result.winner_amount);
+        }
+        
+        msg!("Settled {} matches in one batch", winners.len());
+        
+        Ok(())
+    }
+
+    /// Anyone submits a result signed off-chain by the arbiter (e.g. an automated game
+    /// server), so the arbiter's key never has to sign or pay for the transaction. The
+    /// instruction right before this one must be an ed25519 program instruction verifying
+    /// the arbiter's signature over `signed_result_message(wager, winner, nonce)`, where
+    /// `nonce` is the wager's `series_round` so a result cannot be replayed on a rematch.
+    /// Pays out exactly as `declare_winner`, including the match timeout.
+    pub fn declare_winner_signed(ctx: Context<DeclareWinnerSigned>, winner: u8, nonce: u64) -> Result<()> {
+        // A committed result can only be settled through `reveal_result`
+        require!(
+            ctx.accounts.wager.result_commitment.is_none(),
+            ErrorCode::ResultAlreadyCommitted
+        );
+        
+        let accounts = ctx.accounts;
+        let arbiter = accounts.wager.arbiter;
+        require!(nonce == accounts.wager.series_round, ErrorCode::InvalidSignedResult);
+        let message = signed_result_message(&accounts.wager.key(), winner, nonce);
+        require_ed25519_signature(&accounts.instructions, &arbiter, &message)?;
+        
+        let current_time = Clock::get()?.unix_timestamp;
+        require_arbiter_can_declare(&accounts.wager, arbiter, winner, current_time, true)?;
+        require_escrow_signature(&accounts.wager, accounts.escrow_authority.as_ref())?;
+        let fee_waiver_bps = arbiter_fee_waiver_bps(&accounts.wager, accounts.arbiter_stats.as_ref())?
+            .max(new_player_fee_waiver_bps(&accounts.wager, accounts.player1_stats.as_ref(), accounts.player2_stats.as_ref())?);
+        
+        pay_out_winner(
+            &mut accounts.wager,
+            &accounts.vault,
+            &accounts.winner_account,
+            &accounts.fee_recipient,
+            accounts.secondary_fee_recipient.as_deref(),
+            accounts.insurance_pool.as_mut(),
+            Some(&accounts.arbiter),
+            &accounts.system_program,
+            winner,
+            current_time,
+            fee_waiver_bps,
+        )?;
+        if let Some(arbiter_stats) = accounts.arbiter_stats.as_mut() {
+            arbiter_stats.settled_count = arbiter_stats.settled_count.checked_add(1).unwrap();
+        }
+        record_games_played(accounts.player1_stats.as_mut(), accounts.player2_stats.as_mut());
+        
+        Ok(())
+    }
+
+    /// Arbiter declares a winner with both players co-signing, which waives the match
+    /// timeout (e.g. the arbiter was a few seconds late). Pays out as `declare_winner`;
+    /// only possible until someone refunds the expired match.
+    pub fn declare_winner_with_consent(ctx: Context<DeclareWinnerWithConsent>, winner: u8) -> Result<()> {
+        // A committed result can only be settled through `reveal_result`
+        require!(
+            ctx.accounts.wager.result_commitment.is_none(),
+            ErrorCode::ResultAlreadyCommitted
+        );
+        
+        let accounts = ctx.accounts;
+        let current_time = Clock::get()?.unix_timestamp;
+        require_arbiter_can_declare(&accounts.wager, accounts.arbiter.key(), winner, current_time, false)?;
+        require_escrow_signature(&accounts.wager, accounts.escrow_authority.as_ref())?;
+        let fee_waiver_bps = arbiter_fee_waiver_bps(&accounts.wager, accounts.arbiter_stats.as_ref())?
+            .max(new_player_fee_waiver_bps(&accounts.wager, accounts.player1_stats.as_ref(), accounts.player2_stats.as_ref())?);
+        
+        pay_out_winner(
+            &mut accounts.wager,
+            &accounts.vault,
+            &accounts.winner_account,
+            &accounts.fee_recipient,
+            accounts.secondary_fee_recipient.as_deref(),
+            accounts.insurance_pool.as_mut(),
+            Some(accounts.arbiter.as_ref()),
+            &accounts.system_program,
+            winner,
+            current_time,
+            fee_waiver_bps,
+        )?;
+        if let Some(arbiter_stats) = accounts.arbiter_stats.as_mut() {
+            arbiter_stats.settled_count = arbiter_stats.settled_count.checked_add(1).unwrap();
         }
+        record_games_played(accounts.player1_stats.as_mut(), accounts.player2_stats.as_mut());
         
         Ok(())
     }
 
-    /// Arbiter declares a winner (must be within timeout period)
-    pub fn declare_winner(ctx: Context<DeclareWinner>, winner: u8) -> Result<()> {
+    /// Arbiter binds themselves to a result before it is public by storing
+    /// `sha256(winner || salt)`; the payout happens later in `reveal_result`
+    pub fn commit_result(ctx: Context<CommitResult>, commitment: [u8; 32]) -> Result<()> {
         let wager = &ctx.accounts.wager;
         
-        require!(!wager.is_settled, ErrorCode::WagerAlreadySettled);
+        require!(!wager.is_settled(), ErrorCode::WagerAlreadySettled);
         require!(
             ctx.accounts.arbiter.key() == wager.arbiter,
             ErrorCode::UnauthorizedArbiter
         );
-        require!(
-            wager.player1_deposited && wager.player2_deposited,
-            ErrorCode::BothPlayersNotDeposited
-        );
-        require!(winner == 1 || winner == 2, ErrorCode::InvalidWinner);
+        require!(wager.start_time != 0, ErrorCode::MatchNotStarted);
+        require!(wager.result_commitment.is_none(), ErrorCode::ResultAlreadyCommitted);
+        
+        let current_time = Clock::get()?.unix_timestamp;
+        let elapsed = match_elapsed(wager.start_time, current_time, CLOCK_SKEW_TOLERANCE_SECONDS)
+            .ok_or(ErrorCode::MatchNotStarted)?;
+        require!(elapsed <= TIMEOUT_SECONDS, ErrorCode::TimeoutExpired);
+        
+        let wager = &mut ctx.accounts.wager;
+        wager.result_commitment = Some(commitment);
+        
+        msg!("Result committed");
+        
+        Ok(())
+    }
+
+    /// Arbiter reveals the committed result; pays out exactly as `declare_winner` if
+    /// `sha256(winner || salt)` matches the stored commitment
+    pub fn reveal_result(ctx: Context<DeclareWinner>, winner: u8, salt: [u8; 32]) -> Result<()> {
+        let commitment = ctx.accounts.wager.result_commitment.ok_or(ErrorCode::NoResultCommitted)?;
+        let revealed = anchor_lang::solana_program::hash::hashv(&[&[winner], &salt]);
+        require!(revealed.to_bytes() == commitment, ErrorCode::CommitmentMismatch);
         
         let current_time = Clock::get()?.unix_timestamp;
+        settle_winner(ctx.accounts, winner, current_time)?;
+        
+        Ok(())
+    }
+
+    /// Both players settle a friendly match without the arbiter by co-signing. Each
+    /// player's pick of the winner is passed separately and they must agree.
+    pub fn settle_by_both(ctx: Context<SettleByBoth>, player1_pick: u8, player2_pick: u8) -> Result<()> {
+        let wager = &ctx.accounts.wager;
+        
+        require!(!wager.is_settled(), ErrorCode::WagerAlreadySettled);
         require!(
-            current_time - wager.start_time <= TIMEOUT_SECONDS,
-            ErrorCode::TimeoutExpired
+            wager.player1_deposited() && wager.player2_deposited(),
+            ErrorCode::BothPlayersNotDeposited
         );
+        require!(wager.start_time != 0, ErrorCode::MatchNotStarted);
+        require!(player1_pick == player2_pick, ErrorCode::PlayersDisagree);
+        require!(player1_pick == 1 || player1_pick == 2, ErrorCode::InvalidWinner);
+        require_escrow_signature(wager, ctx.accounts.escrow_authority.as_ref())?;
         
-        let total_pool = wager.wager_amount.checked_mul(2).unwrap();
+        // No arbiter timeout applies: both players consent to the result
+        let current_time = Clock::get()?.unix_timestamp;
+        let elapsed = match_elapsed(wager.start_time, current_time, CLOCK_SKEW_TOLERANCE_SECONDS)
+            .ok_or(ErrorCode::MatchNotStarted)?;
+        require!(elapsed >= wager.min_match_seconds, ErrorCode::MatchTooShort);
         
-        // Deduct initialization cost from the pool before distribution
-        let distributable_pool = total_pool.checked_sub(wager.initialization_cost).unwrap();
+        let accounts = ctx.accounts;
+        pay_out_winner(
+            &mut accounts.wager,
+            &accounts.vault,
+            &accounts.winner_account,
+            &accounts.fee_recipient,
+            accounts.secondary_fee_recipient.as_deref(),
+            accounts.insurance_pool.as_mut(),
+            None,
+            &accounts.system_program,
+            player1_pick,
+            current_time,
+            0,
+        )?;
         
-        let winner_amount = distributable_pool.checked_mul(WINNER_PERCENTAGE).unwrap().checked_div(100).unwrap();
-        let fee_amount = distributable_pool.checked_sub(winner_amount).unwrap();
+        Ok(())
+    }
+
+    /// Player funds their stake and immediately forfeits the match to the opponent, who
+    /// must already have deposited. The pool is paid out as on any other settlement.
+    pub fn deposit_and_concede(ctx: Context<DepositAndConcede>) -> Result<()> {
+        let wager = &ctx.accounts.wager;
+        let player = ctx.accounts.player.key();
         
-        let _winner_pubkey = if winner == 1 {
-            wager.player1
+        require!(!wager.is_settled(), ErrorCode::WagerAlreadySettled);
+        let conceder = wager.player_number(&player).ok_or(ErrorCode::UnauthorizedPlayer)?;
+        let winner = 3 - conceder;
+        let (already_deposited, opponent_deposited) = if conceder == 1 {
+            (wager.player1_deposited(), wager.player2_deposited())
         } else {
-            wager.player2
+            (wager.player2_deposited(), wager.player1_deposited())
         };
+        require!(!already_deposited, ErrorCode::AlreadyDeposited);
+        // The winnings come from the opponent's matched stake; before it lands there is
+        // nothing to concede, and the conceder can cancel instead
+        require!(opponent_deposited, ErrorCode::OpponentNotDeposited);
+        // The match would end the moment it starts, which a minimum duration rules out
+        require!(wager.min_match_seconds == 0, ErrorCode::MatchTooShort);
+        require_escrow_signature(wager, ctx.accounts.escrow_authority.as_ref())?;
         
-        // Transfer from vault using manual lamport manipulation
-        // Transfer winner amount
-        **ctx.accounts.vault.try_borrow_mut_lamports()? -= winner_amount;
-        **ctx.accounts.winner_account.try_borrow_mut_lamports()? += winner_amount;
+        // Fund the conceder's stake
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.player.to_account_info(),
+                to: ctx.accounts.vault.to_account_info(),
+            },
+        );
+        let vault_balance_before = ctx.accounts.vault.lamports();
+        transfer(cpi_context, wager.wager_amount)?;
+        require_vault_credited(&ctx.accounts.vault, vault_balance_before, wager.wager_amount)?;
         
-        // Transfer fee amount
-        **ctx.accounts.vault.try_borrow_mut_lamports()? -= fee_amount;
-        **ctx.accounts.fee_recipient.try_borrow_mut_lamports()? += fee_amount;
+        record_active_game(ctx.accounts.player_activity.as_mut(), &ctx.accounts.wager)?;
         
-        let wager = &mut ctx.accounts.wager;
+        let current_time = Clock::get()?.unix_timestamp;
+        let accounts = ctx.accounts;
+        let wager = &mut accounts.wager;
         
-        wager.winner = Some(winner);
-        wager.is_settled = true;
+        // The conceder's deposit completes the pair, as a deposit instruction's would
+        wager.deposits_completed_time = current_time;
+        set_status(wager, WagerStatus::Active)?;
+        start_match_when_ready(wager, current_time);
         
-        msg!("Winner declared: Player {}", winner);
-        msg!("Winner receives: {} SOL", winner_amount as f64 / 1_000_000_000.0);
-        msg!("Fee: {} SOL", fee_amount as f64 / 1_000_000_000.0);
+        msg!("Player {} conceded to Player {}", conceder, winner);
+        pay_out_winner(
+            wager,
+            &accounts.vault,
+            &accounts.winner_account,
+            &accounts.fee_recipient,
+            accounts.secondary_fee_recipient.as_deref(),
+            accounts.insurance_pool.as_mut(),
+            None,
+            &accounts.system_program,
+            winner,
+            current_time,
+            0,
+        )?;
         
-        Ok(())
+        close_after_settlement(
+            &mut accounts.wager,
+            &accounts.vault,
+            accounts.payer.as_ref(),
+            Some(&accounts.fee_recipient),
+            accounts.pair_stats.as_mut(),
+            &accounts.system_program,
+        )
     }
 
     /// Refund both players if timeout has expired
     pub fn refund(ctx: Context<Refund>) -> Result<()> {
-        let wager = &ctx.accounts.wager;
+        let accounts = ctx.accounts;
+        let current_time = Clock::get()?.unix_timestamp;
+        refund_expired_match(
+            &mut accounts.wager,
+            &accounts.vault,
+            &accounts.player1,
+            &accounts.player2,
+            &accounts.system_program,
+            0,
+            current_time,
+        )?;
         
-        require!(!wager.is_settled, ErrorCode::WagerAlreadySettled);
-        require!(
-            wager.player1_deposited && wager.player2_deposited,
-            ErrorCode::BothPlayersNotDeposited
+        let refund_amount = accounts.wager.refund_amount_each()?;
+        record_receipt(
+            accounts.receipt.as_mut(),
+            &accounts.wager,
+            (0, 0),
+            [refund_amount, refund_amount],
+            receipt_signer(accounts.receipt_payer.as_ref()),
+            current_time,
         );
         
+        close_after_settlement(
+            &mut accounts.wager,
+            &accounts.vault,
+            accounts.payer.as_ref(),
+            accounts.fee_recipient.as_ref(),
+            accounts.pair_stats.as_mut(),
+            &accounts.system_program,
+        )
+    }
+
+    /// Keeper crank for `refund`, so an expired match never stays stuck because neither
+    /// player bothers to refund it: anyone may trigger the refund on the players' behalf
+    /// and earns the wager's `keeper_reward_bps` cut of the refunded pool, plus a tip of up
+    /// to `KEEPER_TIP_LAMPORTS` charged to the players as a share of the platform fee
+    /// their refunds would have paid (see `Wager::keeper_tip_each`)
+    pub fn crank_refund(ctx: Context<CrankRefund>) -> Result<()> {
+        let accounts = ctx.accounts;
         let current_time = Clock::get()?.unix_timestamp;
-        require!(
-            current_time - wager.start_time > TIMEOUT_SECONDS,
-            ErrorCode::TimeoutNotExpired
+        let reward_each = accounts.wager.keeper_reward_each()?;
+        let tip_each = accounts.wager.keeper_tip_each()?;
+        let keeper_cut_each = reward_each.checked_add(tip_each).ok_or(ErrorCode::PoolOverflow)?;
+        refund_expired_match(
+            &mut accounts.wager,
+            &accounts.vault,
+            &accounts.player1,
+            &accounts.player2,
+            &accounts.system_program,
+            keeper_cut_each,
+            current_time,
+        )?;
+        
+        // The players' refunds left the keeper's cut behind in the vault
+        let keeper_cut = keeper_cut_each.checked_mul(2).ok_or(ErrorCode::PoolOverflow)?;
+        pay_from_vault(
+            &accounts.wager,
+            &accounts.vault,
+            &accounts.keeper,
+            &accounts.system_program,
+            keeper_cut,
+        )?;
+        
+        msg!("Keeper reward: {} lamports, tip: {} lamports", reward_each * 2, tip_each * 2);
+        
+        let refund_amount = accounts.wager.refund_amount_each()?.checked_sub(keeper_cut_each).unwrap();
+        record_receipt(
+            accounts.receipt.as_mut(),
+            &accounts.wager,
+            (0, 0),
+            [refund_amount, refund_amount],
+            receipt_signer(accounts.receipt_payer.as_ref()),
+            current_time,
         );
         
-        // Use vault seeds for transfers from vault
-        // Transfer from vault using manual lamport manipulation
-        // Deduct initialization cost from total pool before refunding
-        let total_pool = wager.wager_amount.checked_mul(2).unwrap();
-        let distributable_pool = total_pool.checked_sub(wager.initialization_cost).unwrap();
-        let refund_amount = distributable_pool.checked_div(2).unwrap();
+        close_after_settlement(
+            &mut accounts.wager,
+            &accounts.vault,
+            accounts.payer.as_ref(),
+            accounts.fee_recipient.as_ref(),
+            accounts.pair_stats.as_mut(),
+            &accounts.system_program,
+        )
+    }
+
+    /// Safety net for games that never reach settlement: once `expiry_time` has passed,
+    /// anyone can void the wager and return whatever was deposited, with or without both
+    /// deposits or a started match. Refunds are on the same terms as `refund` (both
+    /// deposited) or `cancel_wager` (one deposited).
+    pub fn void_expired(ctx: Context<CancelWager>) -> Result<()> {
+        let wager = &ctx.accounts.wager;
+        
+        require!(!wager.is_settled(), ErrorCode::WagerAlreadySettled);
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(wager.is_expired(current_time), ErrorCode::WagerNotExpired);
         
-        // Refund player 1 from vault
-        **ctx.accounts.vault.try_borrow_mut_lamports()? -= refund_amount;
-        **ctx.accounts.player1.try_borrow_mut_lamports()? += refund_amount;
+        require_vault_balance(&ctx.accounts.vault, wager.principal()?)?;
         
-        // Refund player 2 from vault
-        **ctx.accounts.vault.try_borrow_mut_lamports()? -= refund_amount;
-        **ctx.accounts.player2.try_borrow_mut_lamports()? += refund_amount;
+        let player1_deposited = wager.player1_deposited();
+        let player2_deposited = wager.player2_deposited();
+        let both_deposited = player1_deposited && player2_deposited;
+        let [stake1, stake2] = wager.stakes();
+        let refund_amount = if both_deposited {
+            wager.refund_amount_each()?
+        } else {
+            // A lone depositor gets their own stake back, less the initialization cost
+            let stake = if player1_deposited { stake1 } else { stake2 };
+            stake
+                .checked_sub(wager.pool_init_cost())
+                .ok_or(ErrorCode::WagerTooSmallForRent)?
+        };
         
+        // Mark settled before moving funds; no winner is recorded
         let wager = &mut ctx.accounts.wager;
         
-        wager.is_settled = true;
+        let status = if both_deposited { WagerStatus::Refunded } else { wager.status.cancelled() };
+        set_status(wager, status)?;
+        
+        if player1_deposited {
+            pay_from_vault(
+                &ctx.accounts.wager,
+                &ctx.accounts.vault,
+                &ctx.accounts.player1,
+                &ctx.accounts.system_program,
+                refund_amount,
+            )?;
+        }
+        if player2_deposited {
+            let player2 = ctx.accounts.player2.as_ref().ok_or(ErrorCode::UnauthorizedPlayer)?;
+            pay_from_vault(
+                &ctx.accounts.wager,
+                &ctx.accounts.vault,
+                player2,
+                &ctx.accounts.system_program,
+                refund_amount,
+            )?;
+        }
+        
+        msg!("Expired wager voided, depositors refunded: {} lamports each", refund_amount);
+        
+        let accounts = ctx.accounts;
+        record_receipt(
+            accounts.receipt.as_mut(),
+            &accounts.wager,
+            (0, 0),
+            [
+                if player1_deposited { refund_amount } else { 0 },
+                if player2_deposited { refund_amount } else { 0 },
+            ],
+            receipt_signer(accounts.receipt_payer.as_ref()),
+            current_time,
+        );
+        
+        emit!(WagerRefunded {
+            wager: accounts.wager.key(),
+            player1_net: if player1_deposited { net_change(refund_amount, stake1) } else { 0 },
+            player2_net: if player2_deposited { net_change(refund_amount, stake2) } else { 0 },
+            tournament_id: accounts.wager.tournament_id,
+        });
+        
+        close_after_settlement(
+            &mut accounts.wager,
+            &accounts.vault,
+            accounts.payer.as_ref(),
+            accounts.fee_recipient.as_ref(),
+            accounts.pair_stats.as_mut(),
+            &accounts.system_program,
+        )
+    }
+
+    /// Arbiter voids an active game (e.g. cheating or a bug) and refunds both players
+    pub fn arbiter_void(ctx: Context<ArbiterVoid>) -> Result<()> {
+        let wager = &ctx.accounts.wager;
+        
+        require!(!wager.is_settled(), ErrorCode::WagerAlreadySettled);
+        require!(
+            ctx.accounts.arbiter.key() == wager.arbiter,
+            ErrorCode::UnauthorizedArbiter
+        );
+        
+        let accounts = ctx.accounts;
+        let refund_amount = void_match(
+            &mut accounts.wager,
+            &accounts.vault,
+            &accounts.player1,
+            &accounts.player2,
+            &accounts.system_program,
+        )?;
+        
+        msg!("Game voided by arbiter, both players refunded: {} lamports each", refund_amount);
         
-        msg!("Refund issued to both players: {} SOL each", refund_amount as f64 / 1_000_000_000.0);
+        Ok(())
+    }
+
+    /// Both players abandon an active game together, neither claiming the win: with
+    /// both signatures the stakes are refunded at once, on the same terms as
+    /// `arbiter_void` and regardless of the match timeout
+    pub fn mutual_void(ctx: Context<MutualVoid>) -> Result<()> {
+        require!(!ctx.accounts.wager.is_settled(), ErrorCode::WagerAlreadySettled);
+        require!(
+            ctx.accounts.player1.is_signer && ctx.accounts.player2.is_signer,
+            ErrorCode::MutualVoidRequiresBothSignatures
+        );
+        
+        let accounts = ctx.accounts;
+        let refund_amount = void_match(
+            &mut accounts.wager,
+            &accounts.vault,
+            &accounts.player1,
+            &accounts.player2,
+            &accounts.system_program,
+        )?;
+        
+        msg!("Game voided by both players, each refunded: {} lamports", refund_amount);
         
         Ok(())
     }
 
     /// Cancel wager and refund deposited player if other player hasn't deposited within timeout
     pub fn cancel_wager(ctx: Context<CancelWager>) -> Result<()> {
+        cancel_and_refund(ctx.accounts, true)
+    }
+
+    /// Cancel before the deposit timeout when both players agree (e.g. player 2 will not
+    /// play after all): with both signatures the deposits are refunded at once, on the
+    /// same terms as `cancel_wager`
+    pub fn mutual_cancel(ctx: Context<CancelWager>) -> Result<()> {
+        require!(
+            ctx.accounts.player1.is_signer
+                && ctx.accounts.player2.as_ref().is_some_and(|player2| player2.is_signer),
+            ErrorCode::MutualCancelRequiresConsent
+        );
+        cancel_and_refund(ctx.accounts, false)
+    }
+
+    /// Winner pulls winnings held in the vault by a wager with a claim window. Claims
+    /// stay possible after the deadline until the winnings are swept.
+    pub fn claim_winnings(ctx: Context<ClaimWinnings>) -> Result<()> {
         let wager = &ctx.accounts.wager;
         
-        require!(!wager.is_settled, ErrorCode::WagerAlreadySettled);
+        require!(wager.unclaimed_winnings > 0, ErrorCode::NothingToClaim);
+        let winner = wager.winner.ok_or(ErrorCode::NothingToClaim)?;
         require!(
-            !(wager.player1_deposited && wager.player2_deposited),
-            ErrorCode::BothPlayersAlreadyDeposited
+            wager.player_number(&ctx.accounts.player.key()) == Some(winner),
+            ErrorCode::UnauthorizedPlayer
         );
-        
-        let current_time = Clock::get()?.unix_timestamp;
         require!(
-            current_time - wager.creation_time > DEPOSIT_TIMEOUT_SECONDS,
-            ErrorCode::DepositTimeoutNotExpired
+            ctx.accounts.winner_account.key() == wager.payout_address(winner),
+            ErrorCode::InvalidWinnerAccount
         );
         
-        let player1_deposited = wager.player1_deposited;
-        let player2_deposited = wager.player2_deposited;
+        // Clear the claim before any value leaves the vault
+        let amount = wager.unclaimed_winnings;
+        require_viable_payout(&ctx.accounts.winner_account, amount)?;
+        let wager = &mut ctx.accounts.wager;
+        wager.unclaimed_winnings = 0;
         
-        // Deduct initialization cost from refund since opponent didn't show up
-        let refund_amount = wager.wager_amount.checked_sub(wager.initialization_cost).unwrap();
+        pay_from_vault(
+            &ctx.accounts.wager,
+            &ctx.accounts.vault,
+            &ctx.accounts.winner_account,
+            &ctx.accounts.system_program,
+            amount,
+        )?;
         
-        // Refund using manual lamport manipulation
-        if player1_deposited {
-            **ctx.accounts.vault.try_borrow_mut_lamports()? -= refund_amount;
-            **ctx.accounts.player1.try_borrow_mut_lamports()? += refund_amount;
-            msg!("Player 1 refunded: {} SOL (after deducting {} SOL initialization cost)", 
-                refund_amount as f64 / 1_000_000_000.0,
-                wager.initialization_cost as f64 / 1_000_000_000.0);
+        msg!("Player {} claimed {} lamports", winner, amount);
+        
+        Ok(())
+    }
+
+    /// Winner pulls held winnings split across a team: one member account per share is
+    /// passed in `remaining_accounts` (writable, in order), and `shares` (basis points,
+    /// each positive, summing to 10000) sets each member's cut. Any rounding remainder goes
+    /// to the first member.
+    pub fn claim_as_team<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ClaimAsTeam<'info>>,
+        shares: Vec<u16>,
+    ) -> Result<()> {
+        let wager = &ctx.accounts.wager;
+        let members = ctx.remaining_accounts;
+        
+        require!(wager.unclaimed_winnings > 0, ErrorCode::NothingToClaim);
+        let winner = wager.winner.ok_or(ErrorCode::NothingToClaim)?;
+        require!(
+            wager.player_number(&ctx.accounts.player.key()) == Some(winner),
+            ErrorCode::UnauthorizedPlayer
+        );
+        require!(members.len() == shares.len(), ErrorCode::InvalidTeamShares);
+        require!(
+            members.iter().all(|member| !is_reserved_address(member.key)),
+            ErrorCode::ReservedPayoutAddress
+        );
+        
+        // Clear the claim before any value leaves the vault
+        let amount = wager.unclaimed_winnings;
+        let payouts = team_payouts(amount, &shares)?;
+        for (member, payout) in members.iter().zip(&payouts) {
+            require_viable_payout(member, *payout)?;
         }
+        let wager = &mut ctx.accounts.wager;
+        wager.unclaimed_winnings = 0;
         
-        if player2_deposited {
-            **ctx.accounts.vault.try_borrow_mut_lamports()? -= refund_amount;
-            **ctx.accounts.player2.try_borrow_mut_lamports()? += refund_amount;
-            msg!("Player 2 refunded: {} SOL (after deducting {} SOL initialization cost)", 
-                refund_amount as f64 / 1_000_000_000.0,
-                wager.initialization_cost as f64 / 1_000_000_000.0);
+        for (member, payout) in members.iter().zip(payouts) {
+            pay_from_vault(
+                &ctx.accounts.wager,
+                &ctx.accounts.vault,
+                member,
+                &ctx.accounts.system_program,
+                payout,
+            )?;
+            msg!("Team member {} received {} lamports", member.key(), payout);
         }
         
+        msg!("Player {} claimed {} lamports for a team of {}", winner, amount, members.len());
+        
+        Ok(())
+    }
+
+    /// Fee recipient or payer sweeps winnings the winner left unclaimed past the claim deadline
+    pub fn sweep_unclaimed(ctx: Context<SweepUnclaimed>) -> Result<()> {
+        let wager = &ctx.accounts.wager;
+        let authority = ctx.accounts.authority.key();
+        
+        require!(wager.unclaimed_winnings > 0, ErrorCode::NothingToClaim);
+        require!(
+            authority == wager.fee_recipient || authority == wager.payer,
+            ErrorCode::UnauthorizedSweep
+        );
+        
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(wager.claim_window_expired(current_time), ErrorCode::ClaimStillOpen);
+        
+        let amount = wager.unclaimed_winnings;
         let wager = &mut ctx.accounts.wager;
+        wager.unclaimed_winnings = 0;
         
-        wager.is_settled = true;
+        pay_from_vault(
+            &ctx.accounts.wager,
+            &ctx.accounts.vault,
+            &ctx.accounts.authority,
+            &ctx.accounts.system_program,
+            amount,
+        )?;
         
-        msg!("Wager cancelled due to incomplete deposits after {} seconds", DEPOSIT_TIMEOUT_SECONDS);
+        msg!("Swept {} lamports of unclaimed winnings to {}", amount, authority);
         
         Ok(())
     }
-}
 
-#[derive(Accounts)]
-#[instruction(player1: Pubkey, player2: Pubkey)]
-pub struct InitializeWager<'info> {
-    #[account(
-        init,
-        payer = payer,
-        space = 8 + Wager::INIT_SPACE,
-        seeds = [b"wager", player1.as_ref(), player2.as_ref()],
-        bump
-    )]
+    /// Close a settled wager, returning rent to the payer and splitting leftover vault dust
+    pub fn close_wager(ctx: Context<CloseWager>) -> Result<()> {
+        let wager = &ctx.accounts.wager;
+        
+        require!(wager.is_settled(), ErrorCode::WagerNotSettled);
+        
+        sweep_vault(
+            &ctx.accounts.wager,
+            &ctx.accounts.vault,
+            &ctx.accounts.payer,
+            &ctx.accounts.fee_recipient,
+            &ctx.accounts.system_program,
+        )?;
+        
+        // Wagers older than pair stats have none to count the settlement in
+        if let Some(pair_stats) = ctx.accounts.pair_stats.as_mut() {
+            pair_stats.settlement_count = pair_stats.settlement_count.checked_add(1).unwrap();
+        }
+        
+        // Wager account rent is returned to the payer by the `close` constraint
+        msg!("Wager closed");
+        
+        Ok(())
+    }
+
+    /// Start a new game between the same players, arbiter and fee recipient after a
+    /// settled one, optionally at a new stake. Initializes a fresh wager and vault
+    /// (seeded with `rematch_wager_seed(prior_wager, nonce)`) carrying over the prior
+    /// wager's players, arbiter, fee recipient and options; the settled wager is left
+    /// untouched, keeping its outcome for `close_wager`. The new PDAs' rent is the new
+    /// game's initialization cost, as for `initialize_wager`, and the rematch counts as a
+    /// new initialization for the same pair.
+    pub fn rematch(ctx: Context<Rematch>, nonce: u64, new_amount: Option<u64>) -> Result<()> {
+        let prior = &ctx.accounts.prior_wager;
+        let payer = ctx.accounts.payer.key();
+        
+        require!(prior.is_settled(), ErrorCode::WagerNotSettled);
+        require!(
+            prior.player_number(&payer).is_some() || payer == prior.payer,
+            ErrorCode::UnauthorizedRematch
+        );
+        let wager_amount = new_amount.unwrap_or(prior.wager_amount);
+        require!(wager_amount > 0, ErrorCode::InvalidWagerAmount);
+        
+        let rent = Rent::get()?;
+        let vault_rent = rent.minimum_balance(0);
+        let initialization_cost = rent
+            .minimum_balance(8 + Wager::INIT_SPACE)
+            .checked_add(vault_rent)
+            .unwrap();
+        if prior.deduct_init_cost_from_pool {
+            require_stake_covers_init_cost(wager_amount, initialization_cost)?;
+        }
+        
+        let clock = Clock::get()?;
+        let current_time = clock.unix_timestamp;
+        ctx.accounts.pair_stats.record_initialization(clock.epoch, current_time)?;
+        
+        // Options carry over; per-game state starts afresh
+        let mut wager = Wager::clone(prior);
+        let series_round = prior.series_round.checked_add(1).unwrap();
+        if !wager.arbiter_rotation.is_empty() {
+            let index = series_round % wager.arbiter_rotation.len() as u64;
+            wager.arbiter = wager.arbiter_rotation[index as usize];
+        }
+        wager.pda_seed = rematch_wager_seed(&prior.key(), nonce);
+        wager.bump = ctx.bumps.wager;
+        wager.vault_bump = ctx.bumps.vault;
+        wager.payer = payer;
+        wager.version = WAGER_VERSION;
+        wager.series_round = series_round;
+        wager.arbiter_changes = 0;
+        wager.wager_amount = wager_amount;
+        wager.creation_time = current_time;
+        wager.expiry_time = current_time.checked_add(WAGER_EXPIRY_SECONDS).unwrap();
+        wager.start_time = 0;
+        wager.first_deposit_time = 0;
+        wager.scheduled_start = 0;
+        wager.winner = None;
+        wager.initialization_cost = initialization_cost;
+        wager.yield_paid = 0;
+        wager.vault_prefund = ctx.accounts.vault.lamports().saturating_sub(vault_rent);
+        wager.result_commitment = None;
+        wager.scores = None;
+        wager.declared_time = 0;
+        wager.unclaimed_winnings = 0;
+        wager.has_side_pool = false;
+        wager.cancelled_time = 0;
+        wager.player1_ready = false;
+        wager.player2_ready = false;
+        wager.deposits_completed_time = 0;
+        wager.settlements_frozen = false;
+        wager.status = WagerStatus::Created;
+        ctx.accounts.wager.set_inner(wager);
+        
+        let wager = &ctx.accounts.wager;
+        msg!("Rematch of {}: {} lamports per player", ctx.accounts.prior_wager.key(), wager_amount);
+        
+        emit!(WagerInitialized {
+            wager: wager.key(),
+            player1: wager.player1,
+            player2: wager.player2,
+            arbiter: wager.arbiter,
+            fee_recipient: wager.fee_recipient,
+            wager_amount,
+            tournament_id: wager.tournament_id,
+            game_ref: wager.game_ref,
+        });
+        
+        Ok(())
+    }
+
+    /// Declare the winner and close both PDAs in one step for single-round matches:
+    /// pays out exactly as `declare_winner`, then sweeps the vault as `close_wager` does
+    pub fn settle_and_close(ctx: Context<SettleAndClose>, winner: u8) -> Result<()> {
+        // A committed result can only be settled through `reveal_result`
+        require!(
+            ctx.accounts.wager.result_commitment.is_none(),
+            ErrorCode::ResultAlreadyCommitted
+        );
+        
+        let accounts = ctx.accounts;
+        let current_time = Clock::get()?.unix_timestamp;
+        require_arbiter_can_declare(&accounts.wager, accounts.arbiter.key(), winner, current_time, true)?;
+        require_escrow_signature(&accounts.wager, accounts.escrow_authority.as_ref())?;
+        let fee_waiver_bps = arbiter_fee_waiver_bps(&accounts.wager, accounts.arbiter_stats.as_ref())?
+            .max(new_player_fee_waiver_bps(&accounts.wager, accounts.player1_stats.as_ref(), accounts.player2_stats.as_ref())?);
+        
+        pay_out_winner(
+            &mut accounts.wager,
+            &accounts.vault,
+            &accounts.winner_account,
+            &accounts.fee_recipient,
+            accounts.secondary_fee_recipient.as_deref(),
+            accounts.insurance_pool.as_mut(),
+            Some(accounts.arbiter.as_ref()),
+            &accounts.system_program,
+            winner,
+            current_time,
+            fee_waiver_bps,
+        )?;
+        if let Some(arbiter_stats) = accounts.arbiter_stats.as_mut() {
+            arbiter_stats.settled_count = arbiter_stats.settled_count.checked_add(1).unwrap();
+        }
+        record_games_played(accounts.player1_stats.as_mut(), accounts.player2_stats.as_mut());
+        
+        sweep_vault(
+            &accounts.wager,
+            &accounts.vault,
+            &accounts.payer,
+            &accounts.fee_recipient,
+            &accounts.system_program,
+        )?;
+        accounts.pair_stats.settlement_count = accounts.pair_stats.settlement_count.checked_add(1).unwrap();
+        
+        // Wager account rent is returned to the payer by the `close` constraint
+        msg!("Wager settled and closed");
+        
+        Ok(())
+    }
+
+    /// Reclaim a wager nobody ever deposited into: once the deposit timeout has passed,
+    /// close both PDAs and return all of their rent to the payer in one step
+    pub fn reclaim_abandoned(ctx: Context<ReclaimAbandoned>) -> Result<()> {
+        let wager = &ctx.accounts.wager;
+        
+        require!(!wager.is_settled(), ErrorCode::WagerAlreadySettled);
+        require!(
+            !wager.player1_deposited() && !wager.player2_deposited(),
+            ErrorCode::WagerNotAbandoned
+        );
+        require!(!wager.has_side_pool, ErrorCode::SidePoolUnsettled);
+        
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(wager.deposit_window_expired(current_time), ErrorCode::DepositTimeoutNotExpired);
+        
+        // With no deposits the vault holds only its rent (plus any pre-funding)
+        let vault_lamports = ctx.accounts.vault.lamports();
+        pay_from_vault(
+            &ctx.accounts.wager,
+            &ctx.accounts.vault,
+            &ctx.accounts.payer,
+            &ctx.accounts.system_program,
+            vault_lamports,
+        )?;
+        
+        // Wager account rent is returned to the payer by the `close` constraint
+        msg!("Abandoned wager reclaimed, payer recovered {} lamports vault rent", vault_lamports);
+        
+        Ok(())
+    }
+
+    /// Payer closes a wager that was cancelled before anyone deposited, recovering the
+    /// full rent of both PDAs (no dust goes to the fee recipient, unlike `close_wager`)
+    pub fn reclaim_init_cost(ctx: Context<ReclaimInitCost>) -> Result<()> {
+        let wager = &ctx.accounts.wager;
+        
+        require!(wager.status.is_cancelled(), ErrorCode::WagerNotCancelled);
+        require!(
+            !wager.player1_deposited() && !wager.player2_deposited(),
+            ErrorCode::WagerNotAbandoned
+        );
+        require!(!wager.has_side_pool, ErrorCode::SidePoolUnsettled);
+        
+        let vault_lamports = ctx.accounts.vault.lamports();
+        pay_from_vault(
+            &ctx.accounts.wager,
+            &ctx.accounts.vault,
+            &ctx.accounts.payer,
+            &ctx.accounts.system_program,
+            vault_lamports,
+        )?;
+        
+        // Wager account rent is returned to the payer by the `close` constraint
+        msg!("Cancelled wager closed, payer recovered {} lamports vault rent", vault_lamports);
+        
+        Ok(())
+    }
+
+    /// The player whose missing deposit got the wager cancelled shows up after all: within
+    /// `REOPEN_WINDOW_SECONDS` of the cancellation (and before the wager is closed) they
+    /// can reopen it, resetting it to `Created` so that both players deposit afresh. The
+    /// refunded player keeps their refund until they re-deposit, and the initialization
+    /// cost the cancellation retained stays in the vault, excluded from the new pool, so
+    /// it is not charged twice.
+    pub fn reopen_wager(ctx: Context<ReopenWager>) -> Result<()> {
+        let wager = &ctx.accounts.wager;
+        let player = ctx.accounts.player.key();
+        
+        require!(wager.status.is_cancelled(), ErrorCode::WagerNotCancelled);
+        // Only a one-sided cancellation has a missing player to come back
+        require!(
+            wager.player1_deposited() != wager.player2_deposited(),
+            ErrorCode::NotReopenable
+        );
+        let missing_player = if wager.player1_deposited() { wager.player2 } else { wager.player1 };
+        require!(player == missing_player, ErrorCode::UnauthorizedPlayer);
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(
+            current_time <= wager.cancelled_time.saturating_add(REOPEN_WINDOW_SECONDS),
+            ErrorCode::ReopenWindowExpired
+        );
+        
+        let vault_rent = Rent::get()?.minimum_balance(0);
+        let retained = ctx.accounts.vault.lamports().saturating_sub(vault_rent);
+        
+        let wager = &mut ctx.accounts.wager;
+        wager.creation_time = current_time;
+        wager.expiry_time = current_time
+            .max(wager.scheduled_start)
+            .checked_add(WAGER_EXPIRY_SECONDS)
+            .unwrap();
+        wager.start_time = 0;
+        wager.first_deposit_time = 0;
+        wager.initialization_cost = 0;
+        wager.vault_prefund = retained;
+        wager.cancelled_time = 0;
+        wager.player1_ready = false;
+        wager.player2_ready = false;
+        wager.deposits_completed_time = 0;
+        wager.settlements_frozen = false;
+        set_status(wager, WagerStatus::Created)?;
+        
+        msg!("Wager reopened by {}", player);
+        
+        Ok(())
+    }
+
+    /// Ready handshake of a `require_ready` wager: once both players have deposited, each
+    /// signals they are ready and the match timer starts when both are. If the handshake
+    /// is not complete `READY_TIMEOUT_SECONDS` after the second deposit, anyone can call
+    /// this to ready both players, so a silent player cannot hold the stakes hostage.
+    pub fn ready_up(ctx: Context<ReadyUp>) -> Result<()> {
+        let wager = &ctx.accounts.wager;
+        let authority = ctx.accounts.authority.key();
+        
+        require!(!wager.is_settled(), ErrorCode::WagerAlreadySettled);
+        require!(wager.require_ready, ErrorCode::ReadyNotRequired);
+        require!(
+            wager.player1_deposited() && wager.player2_deposited(),
+            ErrorCode::BothPlayersNotDeposited
+        );
+        require!(wager.start_time == 0, ErrorCode::MatchAlreadyStarted);
+        
+        let current_time = Clock::get()?.unix_timestamp;
+        let ready_timed_out = current_time
+            > wager.deposits_completed_time.checked_add(READY_TIMEOUT_SECONDS).unwrap();
+        
+        let wager = &mut ctx.accounts.wager;
+        if ready_timed_out {
+            wager.player1_ready = true;
+            wager.player2_ready = true;
+            msg!("Ready timeout passed, both players readied automatically");
+        } else {
+            let player = wager.player_number(&authority).ok_or(ErrorCode::UnauthorizedPlayer)?;
+            if player == 1 {
+                wager.player1_ready = true;
+            } else {
+                wager.player2_ready = true;
+            }
+            msg!("Player {} is ready", player);
+        }
+        
+        start_match_when_ready(wager, current_time);
+        
+        Ok(())
+    }
+
+    /// Replace the arbiter before the match starts, authorized by the current arbiter
+    /// or by both players signing together
+    pub fn update_arbiter(ctx: Context<UpdateArbiter>, new_arbiter: Pubkey) -> Result<()> {
+        let wager = &ctx.accounts.wager;
+        let authority = ctx.accounts.authority.key();
+        let co_signer = ctx.accounts.co_signer.as_ref().map(|signer| signer.key());
+        
+        require!(!wager.is_settled(), ErrorCode::WagerAlreadySettled);
+        require!(
+            !(wager.player1_deposited() && wager.player2_deposited()),
+            ErrorCode::CannotChangeArbiterAfterStart
+        );
+        
+        let signed_by_arbiter = authority == wager.arbiter;
+        let signed_by_both_players = wager.are_both_players(&authority, co_signer.as_ref());
+        require!(
+            signed_by_arbiter || signed_by_both_players,
+            ErrorCode::UnauthorizedArbiterChange
+        );
+        require!(wager.arbiter_changes < MAX_ARBITER_CHANGES, ErrorCode::TooManyArbiterChanges);
+        
+        let wager = &mut ctx.accounts.wager;
+        let old_arbiter = wager.arbiter;
+        wager.arbiter = new_arbiter;
+        wager.arbiter_changes += 1;
+        
+        msg!("Arbiter changed from {} to {}", old_arbiter, new_arbiter);
+        
+        Ok(())
+    }
+
+    /// Point the fee at a new address (e.g. a rotated or compromised wallet) any time
+    /// before settlement, authorized by the current fee recipient
+    pub fn update_fee_recipient(ctx: Context<UpdateFeeRecipient>, new_fee_recipient: Pubkey) -> Result<()> {
+        let wager = &ctx.accounts.wager;
+        
+        require!(!wager.is_settled(), ErrorCode::WagerAlreadySettled);
+        require!(
+            ctx.accounts.fee_recipient.key() == wager.fee_recipient,
+            ErrorCode::UnauthorizedFeeRecipient
+        );
+        require!(!is_reserved_address(&new_fee_recipient), ErrorCode::ReservedPayoutAddress);
+        
+        let wager = &mut ctx.accounts.wager;
+        let old_fee_recipient = wager.fee_recipient;
+        wager.fee_recipient = new_fee_recipient;
+        require_fee_recipient_not_player(wager)?;
+        
+        msg!("Fee recipient changed from {} to {}", old_fee_recipient, new_fee_recipient);
+        
+        Ok(())
+    }
+
+    /// Arbiter freezes (or unfreezes) settlement during an incident: no result can be paid
+    /// out while frozen, but refunds and voids stay open so the stakes are never locked
+    pub fn set_settlements_frozen(ctx: Context<SetSettlementsFrozen>, frozen: bool) -> Result<()> {
+        let wager = &ctx.accounts.wager;
+        
+        require!(!wager.is_settled(), ErrorCode::WagerAlreadySettled);
+        require!(
+            ctx.accounts.arbiter.key() == wager.arbiter,
+            ErrorCode::UnauthorizedArbiter
+        );
+        
+        ctx.accounts.wager.settlements_frozen = frozen;
+        
+        msg!("Settlements {}", if frozen { "frozen" } else { "unfrozen" });
+        
+        Ok(())
+    }
+
+    /// Renegotiate the stake before either player deposits, without recreating the PDAs.
+    /// Requires both players' signatures, or the payer who funded the PDAs.
+    pub fn update_wager_amount(ctx: Context<UpdateWagerAmount>, new_amount: u64) -> Result<()> {
+        let wager = &ctx.accounts.wager;
+        let authority = ctx.accounts.authority.key();
+        let co_signer = ctx.accounts.co_signer.as_ref().map(|signer| signer.key());
+        
+        require!(!wager.is_settled(), ErrorCode::WagerAlreadySettled);
+        require!(
+            !wager.player1_deposited() && !wager.player2_deposited(),
+            ErrorCode::CannotChangeAmountAfterDeposit
+        );
+        require!(new_amount > 0, ErrorCode::InvalidWagerAmount);
+        require_stake_covers_init_cost(new_amount, wager.pool_init_cost())?;
+        
+        let signed_by_payer = authority == wager.payer;
+        let signed_by_both_players = wager.are_both_players(&authority, co_signer.as_ref());
+        require!(
+            signed_by_payer || signed_by_both_players,
+            ErrorCode::UnauthorizedAmountChange
+        );
+        
+        let wager = &mut ctx.accounts.wager;
+        let old_amount = wager.wager_amount;
+        wager.wager_amount = new_amount;
+        
+        msg!(
+            "Wager amount changed from {} lamports to {} lamports",
+            old_amount,
+            new_amount
+        );
+        
+        Ok(())
+    }
+
+    /// Player registers a different wallet (e.g. a cold wallet) to receive their winnings.
+    /// Passing `None` clears the override so winnings go to the player's own key.
+    pub fn set_payout_address(ctx: Context<SetPayoutAddress>, payout_address: Option<Pubkey>) -> Result<()> {
+        let wager = &mut ctx.accounts.wager;
+        let player = ctx.accounts.player.key();
+        
+        require!(!wager.is_settled(), ErrorCode::WagerAlreadySettled);
+        if let Some(address) = payout_address {
+            require!(!is_reserved_address(&address), ErrorCode::ReservedPayoutAddress);
+        }
+        
+        let seat = wager.player_number(&player).ok_or(ErrorCode::UnauthorizedPlayer)?;
+        if seat == 1 {
+            wager.player1_payout = payout_address;
+        } else {
+            wager.player2_payout = payout_address;
+        }
+        msg!("Player {} payout address: {}", seat, wager.payout_address(seat));
+        
+        Ok(())
+    }
+
+    /// Payout breakdown if the fully funded match were declared now, without settling
+    /// by the arbiter (read-only; call via simulation). Excludes time-dependent rebates,
+    /// waivers and yield.
+    pub fn preview_payout(ctx: Context<ReadWager>) -> Result<PayoutPreview> {
+        let wager = &ctx.accounts.wager;
+        let principal = wager.total_pool()?;
+        let (winner_amount, fee_amount) = wager.split_pool(principal);
+        let arbiter_fee = wager.arbiter_fee(principal);
+        
+        Ok(PayoutPreview {
+            winner_amount: winner_amount.checked_sub(arbiter_fee).unwrap(),
+            fee_amount,
+            initialization_cost: wager.pool_init_cost(),
+            arbiter_fee,
+        })
+    }
+
+    /// Bitmask of the instructions currently valid for this wager (read-only), returned
+    /// as return data so clients can read it via simulation without parsing logs
+    pub fn query_actions(ctx: Context<ReadWager>) -> Result<u16> {
+        let current_time = Clock::get()?.unix_timestamp;
+        Ok(ctx.accounts.wager.available_actions(current_time))
+    }
+
+    /// Whether `key` is a player, the arbiter or the fee recipient of this wager (read-only),
+    /// returned as return data for clients
+    pub fn query_participant(ctx: Context<ReadWager>, key: Pubkey) -> Result<bool> {
+        Ok(ctx.accounts.wager.is_participant(&key))
+    }
+
+    /// Read-only check that the program runs under the id it was built with (`declare_id!`),
+    /// returning that id. Lets deploy scripts catch a build for one cluster's id deployed
+    /// under another before any wager is created.
+    pub fn verify_program(ctx: Context<VerifyProgram>) -> Result<Pubkey> {
+        require_keys_eq!(*ctx.program_id, crate::ID, ErrorCode::ProgramIdMismatch);
+        Ok(crate::ID)
+    }
+
+    /// Log the wager's packed status byte for cheap polling (read-only)
+    pub fn log_status_byte(ctx: Context<ReadWager>) -> Result<()> {
+        let status = ctx.accounts.wager.status_byte();
+        
+        msg!("Status byte: {}", status);
+        
+        Ok(())
+    }
+
+    /// Rewrite a v1 wager as the current layout. The account is grown to the current size
+    /// first (payer covers the extra rent), so the fields v2 added deserialize as
+    /// zero/`None` defaults before the ones that need others are backfilled.
+    pub fn migrate_wager(ctx: Context<MigrateWager>) -> Result<()> {
+        let wager_info = ctx.accounts.wager.to_account_info();
+        // Only wager accounts can be migrated
+        require!(
+            wager_info.try_borrow_data()?.starts_with(Wager::DISCRIMINATOR),
+            anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch
+        );
+        
+        // Grow the account to the current layout, with the payer covering the extra rent
+        let new_len = 8 + Wager::INIT_SPACE;
+        if wager_info.data_len() < new_len {
+            let shortfall = Rent::get()?
+                .minimum_balance(new_len)
+                .saturating_sub(wager_info.lamports());
+            if shortfall > 0 {
+                let cpi_context = CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.payer.to_account_info(),
+                        to: wager_info.clone(),
+                    },
+                );
+                transfer(cpi_context, shortfall)?;
+            }
+            wager_info.resize(new_len)?;
+        }
+        
+        let mut wager = Wager::try_deserialize(&mut &wager_info.try_borrow_data()?[..])?;
+        require!(wager.version < WAGER_VERSION, ErrorCode::WagerAlreadyMigrated);
+        
+        // v1 tracked the lifecycle in its deposit and settlement flags
+        wager.status = wager.legacy_status();
+        // v1 always rounded the fee down
+        wager.round_fee_down = true;
+        // and expires as if it had been created with an expiry
+        wager.expiry_time = wager
+            .creation_time
+            .checked_add(WAGER_EXPIRY_SECONDS)
+            .unwrap();
+        // v1 PDAs are all derived from player 2, with the pair's stats on the same seed
+        wager.pda_seed = wager.player2;
+        wager.pair_seed = wager.player2;
+        // v1 did not record the payer; player 1 takes its place, so `close_wager` returns
+        // the rent to a real account
+        wager.payer = wager.player1;
+        wager.version = WAGER_VERSION;
+        wager.try_serialize(&mut &mut wager_info.try_borrow_mut_data()?[..])?;
+        
+        msg!("Wager migrated from layout v1 to v{}", WAGER_VERSION);
+        
+        Ok(())
+    }
+
+    /// Open a side pool on a wager: third parties back player 1 or player 2 into the
+    /// `["side_vault", wager, 1|2]` vaults until both players have deposited. The wager
+    /// cannot be closed until the pool is settled with `settle_side_pool`.
+    pub fn initialize_side_pool(ctx: Context<InitializeSidePool>) -> Result<()> {
+        let wager = &ctx.accounts.wager;
+        
+        require!(!wager.is_settled() && wager.start_time == 0, ErrorCode::SideBetsClosed);
+        
+        let side_pool = &mut ctx.accounts.side_pool;
+        side_pool.wager = wager.key();
+        side_pool.fee_recipient = wager.fee_recipient;
+        side_pool.total_backing = [0, 0];
+        side_pool.winner = None;
+        side_pool.is_settled = false;
+        side_pool.bump = ctx.bumps.side_pool;
+        side_pool.vault_bumps = [ctx.bumps.side_vault_p1, ctx.bumps.side_vault_p2];
+        
+        ctx.accounts.wager.has_side_pool = true;
+        
+        msg!("Side pool opened");
+        
+        Ok(())
+    }
+
+    /// Back `player` (1 or 2) with `amount` lamports, recorded in the backer's `SideBet`.
+    /// Bets close once both players have deposited.
+    pub fn place_side_bet(ctx: Context<PlaceSideBet>, player: u8, amount: u64) -> Result<()> {
+        let wager = &ctx.accounts.wager;
+        
+        require!(
+            !wager.is_settled() && wager.start_time == 0 && !ctx.accounts.side_pool.is_settled,
+            ErrorCode::SideBetsClosed
+        );
+        require!(player == 1 || player == 2, ErrorCode::InvalidWinner);
+        require!(amount > 0, ErrorCode::InvalidSideBetAmount);
+        
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.backer.to_account_info(),
+                to: ctx.accounts.side_vault.to_account_info(),
+            },
+        );
+        transfer(cpi_context, amount)?;
+        
+        let side_pool = &mut ctx.accounts.side_pool;
+        let side = (player - 1) as usize;
+        side_pool.total_backing[side] = side_pool.total_backing[side].checked_add(amount).unwrap();
+        
+        let side_bet = &mut ctx.accounts.side_bet;
+        side_bet.wager = ctx.accounts.wager.key();
+        side_bet.backer = ctx.accounts.backer.key();
+        side_bet.player = player;
+        side_bet.amount = amount;
+        side_bet.bump = ctx.bumps.side_bet;
+        
+        msg!("Side bet: {} lamports on Player {}", amount, player);
+        
+        Ok(())
+    }
+
+    /// Record the wager's outcome on its side pool so backers can claim, and release the
+    /// wager for closing. Anyone can call it once the wager is settled, or once it was
+    /// abandoned without deposits (backers are then refunded).
+    pub fn settle_side_pool(ctx: Context<SettleSidePool>) -> Result<()> {
+        let wager = &ctx.accounts.wager;
+        
+        require!(!ctx.accounts.side_pool.is_settled, ErrorCode::SidePoolAlreadySettled);
+        let current_time = Clock::get()?.unix_timestamp;
+        let abandoned = !wager.player1_deposited()
+            && !wager.player2_deposited()
+            && wager.deposit_window_expired(current_time);
+        require!(wager.is_settled() || abandoned, ErrorCode::WagerNotSettled);
+        
+        let winner = wager.winner;
+        let side_pool = &mut ctx.accounts.side_pool;
+        side_pool.winner = winner;
+        side_pool.is_settled = true;
+        
+        ctx.accounts.wager.has_side_pool = false;
+        
+        match winner {
+            Some(winner) => msg!("Side pool settled: Player {} backers win", winner),
+            None => msg!("Side pool settled without a winner: stakes refunded"),
+        }
+        
+        Ok(())
+    }
+
+    /// Backer collects a settled side bet, closing it. Winning backers get their stake
+    /// back plus a pro-rata share of the losing side's pool less the fee; losing backers
+    /// only recover the bet's rent.
+    pub fn claim_side_bet(ctx: Context<ClaimSideBet>) -> Result<()> {
+        let side_pool = &ctx.accounts.side_pool;
+        let side_bet = &ctx.accounts.side_bet;
+        
+        require!(side_pool.is_settled, ErrorCode::SidePoolNotSettled);
+        
+        let SideBetPayout { stake, winnings, fee_amount } =
+            side_pool.side_bet_payout(side_bet.player, side_bet.amount)?;
+        let (own_vault, opposing_vault) = if side_bet.player == 1 {
+            (&ctx.accounts.side_vault_p1, &ctx.accounts.side_vault_p2)
+        } else {
+            (&ctx.accounts.side_vault_p2, &ctx.accounts.side_vault_p1)
+        };
+        let opposing = 3 - side_bet.player;
+        let backer = ctx.accounts.backer.to_account_info();
+        let system_program = &ctx.accounts.system_program;
+        
+        pay_from_side_vault(side_pool, side_bet.player, own_vault, &backer, system_program, stake)?;
+        pay_from_side_vault(side_pool, opposing, opposing_vault, &backer, system_program, winnings)?;
+        pay_from_side_vault(side_pool, opposing, opposing_vault, &ctx.accounts.fee_recipient, system_program, fee_amount)?;
+        
+        msg!("Side bet claimed: {} lamports", stake.checked_add(winnings).unwrap());
+        
+        Ok(())
+    }
+
+    /// Both players set the minimum time between initializations of their pair's wager,
+    /// so a squatter cannot re-create it the moment it is cancelled or closed
+    pub fn set_pair_cooldown(ctx: Context<SetPairCooldown>, cooldown_seconds: i64) -> Result<()> {
+        require!(
+            (0..=MAX_PAIR_INIT_COOLDOWN_SECONDS).contains(&cooldown_seconds),
+            ErrorCode::InvalidInitCooldown
+        );
+        
+        ctx.accounts.pair_stats.init_cooldown_seconds = cooldown_seconds;
+        
+        msg!("Pair initialization cooldown set to {} seconds", cooldown_seconds);
+        
+        Ok(())
+    }
+
+    /// Create the signing player's activity account (`["activity", player]`), which caps how
+    /// many wagers they can have open at once. Deposits that pass it in count towards the cap.
+    pub fn initialize_player_activity(ctx: Context<InitializePlayerActivity>, max_active_games: u16) -> Result<()> {
+        require!(
+            max_active_games > 0 && max_active_games as usize <= MAX_ACTIVE_GAMES,
+            ErrorCode::InvalidActiveGamesCap
+        );
+        
+        let player_activity = &mut ctx.accounts.player_activity;
+        player_activity.player = ctx.accounts.player.key();
+        player_activity.max_active_games = max_active_games;
+        player_activity.active_wagers = Vec::new();
+        player_activity.bump = ctx.bumps.player_activity;
+        
+        msg!("Player activity initialized: at most {} open wagers", max_active_games);
+        
+        Ok(())
+    }
+
+    /// Stop counting a wager against a player's cap once it is settled (or closed).
+    /// Permissionless, so anyone can free the slot.
+    pub fn release_active_game(ctx: Context<ReleaseActiveGame>) -> Result<()> {
+        let wager_info = &ctx.accounts.wager;
+        // A closed wager has been garbage collected and no longer holds anything
+        if !wager_info.data_is_empty() {
+            require!(wager_info.owner == &crate::ID, ErrorCode::WagerNotSettled);
+            let data = wager_info.try_borrow_data()?;
+            let wager = Wager::try_deserialize(&mut &data[..])?;
+            require!(wager.is_settled(), ErrorCode::WagerNotSettled);
+        }
+        
+        let player_activity = &mut ctx.accounts.player_activity;
+        let index = player_activity
+            .active_wagers
+            .iter()
+            .position(|key| key == wager_info.key)
+            .ok_or(ErrorCode::GameNotActive)?;
+        player_activity.active_wagers.swap_remove(index);
+        
+        msg!("Released wager {}: {} open", wager_info.key, player_activity.active_wagers.len());
+        
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(player1: Pubkey, player2: Pubkey)]
+pub struct InitializeWager<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Wager::INIT_SPACE,
+        seeds = [b"wager", player1.as_ref(), player2.as_ref()],
+        bump
+    )]
+    pub wager: Account<'info, Wager>,
+    /// CHECK: Vault PDA for holding SOL deposits (no data, just SOL storage). Owned by the
+    /// system program so payouts are plain transfers signed with the vault seeds.
+    #[account(
+        init,
+        payer = payer,
+        space = 0,
+        owner = anchor_lang::system_program::ID,
+        seeds = [b"vault", player1.as_ref(), player2.as_ref()],
+        bump
+    )]
+    pub vault: AccountInfo<'info>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + PairStats::INIT_SPACE,
+        seeds = [b"pair", player1.as_ref(), player2.as_ref()],
+        bump
+    )]
+    pub pair_stats: Account<'info, PairStats>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(player1: Pubkey, nonce: u64)]
+pub struct InitializeOpenWager<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Wager::INIT_SPACE,
+        seeds = [b"wager", player1.as_ref(), open_wager_seed(nonce).as_ref()],
+        bump
+    )]
+    pub wager: Account<'info, Wager>,
+    /// CHECK: Vault PDA for holding SOL deposits, as in `InitializeWager`
+    #[account(
+        init,
+        payer = payer,
+        space = 0,
+        owner = anchor_lang::system_program::ID,
+        seeds = [b"vault", player1.as_ref(), open_wager_seed(nonce).as_ref()],
+        bump
+    )]
+    pub vault: AccountInfo<'info>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + PairStats::INIT_SPACE,
+        seeds = [b"pair", player1.as_ref(), open_wager_seed(nonce).as_ref()],
+        bump
+    )]
+    pub pair_stats: Account<'info, PairStats>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(player1: Pubkey, house: Pubkey)]
+pub struct InitializeHouseWager<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Wager::INIT_SPACE,
+        seeds = [b"wager", player1.as_ref(), house.as_ref()],
+        bump
+    )]
+    pub wager: Account<'info, Wager>,
+    /// CHECK: Vault PDA for holding SOL deposits, as in `InitializeWager`
+    #[account(
+        init,
+        payer = payer,
+        space = 0,
+        owner = anchor_lang::system_program::ID,
+        seeds = [b"vault", player1.as_ref(), house.as_ref()],
+        bump
+    )]
+    pub vault: AccountInfo<'info>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + PairStats::INIT_SPACE,
+        seeds = [b"pair", player1.as_ref(), house.as_ref()],
+        bump
+    )]
+    pub pair_stats: Account<'info, PairStats>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(arbiter: Pubkey)]
+pub struct InitializeArbiterStats<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + ArbiterStats::INIT_SPACE,
+        seeds = [b"arbiter", arbiter.as_ref()],
+        bump
+    )]
+    pub arbiter_stats: Account<'info, ArbiterStats>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(player: Pubkey)]
+pub struct InitializePlayerStats<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + PlayerStats::INIT_SPACE,
+        seeds = [b"player_stats", player.as_ref()],
+        bump
+    )]
+    pub player_stats: Account<'info, PlayerStats>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeInsurancePool<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + InsurancePool::INIT_SPACE,
+        seeds = [b"insurance", authority.key().as_ref()],
+        bump
+    )]
+    pub insurance_pool: Account<'info, InsurancePool>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InsurancePayout<'info> {
+    #[account(
+        mut,
+        seeds = [b"insurance", authority.key().as_ref()],
+        bump = insurance_pool.bump,
+        has_one = authority @ ErrorCode::UnauthorizedInsuranceAuthority
+    )]
+    pub insurance_pool: Account<'info, InsurancePool>,
+    pub authority: Signer<'info>,
+    /// CHECK: Receives the claim payout
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeFeeVault<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + FeeVault::INIT_SPACE,
+        seeds = [b"fee_vault", authority.key().as_ref()],
+        bump
+    )]
+    pub fee_vault: Account<'info, FeeVault>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFees<'info> {
+    #[account(
+        mut,
+        seeds = [b"fee_vault", authority.key().as_ref()],
+        bump = fee_vault.bump,
+        has_one = authority @ ErrorCode::UnauthorizedFeeVaultAuthority
+    )]
+    pub fee_vault: Account<'info, FeeVault>,
+    pub authority: Signer<'info>,
+    /// CHECK: Receives the withdrawn fees
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct DeclareWinnersBatch<'info> {
+    #[account(mut)]
+    pub arbiter: Signer<'info>,
+    /// CHECK: Fee recipient shared by every wager in the batch (checked per wager)
+    #[account(mut)]
+    pub fee_recipient: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DepositPlayer1<'info> {
+    #[account(
+        mut,
+        seeds = [b"wager", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.bump
+    )]
+    pub wager: Account<'info, Wager>,
+    /// CHECK: Vault PDA for holding SOL deposits
+    #[account(
+        mut,
+        seeds = [b"vault", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.vault_bump
+    )]
+    pub vault: AccountInfo<'info>,
+    #[account(mut)]
+    pub player1: Signer<'info>,
+    /// Player 1's open-wager counter; when passed, the deposit counts towards its cap
+    #[account(
+        mut,
+        seeds = [b"activity", player1.key().as_ref()],
+        bump = player_activity.bump
+    )]
+    pub player_activity: Option<Account<'info, PlayerActivity>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DepositPlayer2<'info> {
+    #[account(
+        mut,
+        seeds = [b"wager", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.bump
+    )]
+    pub wager: Account<'info, Wager>,
+    /// CHECK: Vault PDA for holding SOL deposits
+    #[account(
+        mut,
+        seeds = [b"vault", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.vault_bump
+    )]
+    pub vault: AccountInfo<'info>,
+    #[account(mut)]
+    pub player2: Signer<'info>,
+    /// Player 2's open-wager counter; when passed, the deposit counts towards its cap
+    #[account(
+        mut,
+        seeds = [b"activity", player2.key().as_ref()],
+        bump = player_activity.bump
+    )]
+    pub player_activity: Option<Account<'info, PlayerActivity>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DeclareWinner<'info> {
+    #[account(
+        mut,
+        seeds = [b"wager", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.bump
+    )]
+    pub wager: Account<'info, Wager>,
+    /// CHECK: Vault PDA for holding SOL deposits
+    #[account(
+        mut,
+        seeds = [b"vault", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.vault_bump
+    )]
+    pub vault: AccountInfo<'info>,
+    /// Receives the arbiter fee, if the wager has one
+    #[account(mut)]
+    pub arbiter: Signer<'info>,
+    /// CHECK: This is the winner account (either player1 or player2)
+    #[account(mut)]
+    pub winner_account: AccountInfo<'info>,
+    /// CHECK: This is the fee recipient account
+    #[account(mut, address = wager.fee_recipient @ ErrorCode::InvalidFeeRecipient)]
+    pub fee_recipient: AccountInfo<'info>,
+    /// CHECK: Secondary fee recipient, required when the wager has a fee split (validated in `pay_fee`)
+    #[account(mut)]
+    pub secondary_fee_recipient: Option<UncheckedAccount<'info>>,
+    /// Insurance pool receiving the insurance leg, required when the wager has one (validated in `pay_fee`)
+    #[account(mut)]
+    pub insurance_pool: Option<Account<'info, InsurancePool>>,
+    /// Compliance co-signer, required when the wager names an escrow authority (validated in `require_escrow_signature`)
+    pub escrow_authority: Option<Signer<'info>>,
+    /// The arbiter's settled-match counter, required when the wager has a fee waiver
+    #[account(
+        mut,
+        seeds = [b"arbiter", wager.arbiter.as_ref()],
+        bump = arbiter_stats.bump
+    )]
+    pub arbiter_stats: Option<Account<'info, ArbiterStats>>,
+    /// Player 1's game counter, required when the wager has a new-player fee waiver
+    #[account(
+        mut,
+        seeds = [b"player_stats", wager.player1.as_ref()],
+        bump = player1_stats.bump
+    )]
+    pub player1_stats: Option<Account<'info, PlayerStats>>,
+    /// Player 2's game counter, required when the wager has a new-player fee waiver
+    #[account(
+        mut,
+        seeds = [b"player_stats", wager.player2.as_ref()],
+        bump = player2_stats.bump
+    )]
+    pub player2_stats: Option<Account<'info, PlayerStats>>,
+    /// Audit record of this round's outcome (`["receipt", wager, series_round]`), written
+    /// when passed and kept after the wager is closed
+    #[account(
+        init,
+        payer = receipt_payer,
+        space = 8 + SettlementReceipt::INIT_SPACE,
+        seeds = [b"receipt", wager.key().as_ref(), &wager.series_round.to_le_bytes()],
+        bump
+    )]
+    pub receipt: Option<Account<'info, SettlementReceipt>>,
+    /// Pays for the receipt; required with it
+    #[account(mut)]
+    pub receipt_payer: Option<Signer<'info>>,
+    /// CHECK: Original payer, receiving the rent when the wager closes on settlement
+    #[account(mut, address = wager.payer @ ErrorCode::InvalidPayer)]
+    pub payer: Option<AccountInfo<'info>>,
+    /// The pair's stats, required when the wager closes on settlement
+    #[account(
+        mut,
+        seeds = [b"pair", wager.player1.as_ref(), wager.pair_seed.as_ref()],
+        bump = pair_stats.bump
+    )]
+    pub pair_stats: Option<Account<'info, PairStats>>,
+    /// CHECK: Instructions sysvar, required when the wager has strict isolation (validated in `require_isolated`)
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+    /// CHECK: Player 1, refunded when `declare_result` is a draw
+    #[account(mut, address = wager.player1 @ ErrorCode::UnauthorizedPlayer)]
+    pub player1: Option<AccountInfo<'info>>,
+    /// CHECK: Player 2, refunded when `declare_result` is a draw
+    #[account(mut, address = wager.player2 @ ErrorCode::UnauthorizedPlayer)]
+    pub player2: Option<AccountInfo<'info>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DeclareWinnerSigned<'info> {
+    #[account(
+        mut,
+        seeds = [b"wager", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.bump
+    )]
+    pub wager: Account<'info, Wager>,
+    /// CHECK: Vault PDA for holding SOL deposits
+    #[account(
+        mut,
+        seeds = [b"vault", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.vault_bump
+    )]
+    pub vault: AccountInfo<'info>,
+    /// CHECK: The wager's arbiter, receiving the arbiter fee if the wager has one; it does not sign
+    #[account(mut, address = wager.arbiter @ ErrorCode::UnauthorizedArbiter)]
+    pub arbiter: AccountInfo<'info>,
+    /// CHECK: This is the winner account (either player1 or player2)
+    #[account(mut)]
+    pub winner_account: AccountInfo<'info>,
+    /// CHECK: This is the fee recipient account
+    #[account(mut, address = wager.fee_recipient @ ErrorCode::InvalidFeeRecipient)]
+    pub fee_recipient: AccountInfo<'info>,
+    /// CHECK: Secondary fee recipient, required when the wager has a fee split (validated in `pay_fee`)
+    #[account(mut)]
+    pub secondary_fee_recipient: Option<UncheckedAccount<'info>>,
+    /// Insurance pool receiving the insurance leg, required when the wager has one (validated in `pay_fee`)
+    #[account(mut)]
+    pub insurance_pool: Option<Account<'info, InsurancePool>>,
+    /// Compliance co-signer, required when the wager names an escrow authority (validated in `require_escrow_signature`)
+    pub escrow_authority: Option<Signer<'info>>,
+    /// The arbiter's settled-match counter, required when the wager has a fee waiver
+    #[account(
+        mut,
+        seeds = [b"arbiter", wager.arbiter.as_ref()],
+        bump = arbiter_stats.bump
+    )]
+    pub arbiter_stats: Option<Account<'info, ArbiterStats>>,
+    /// Player 1's game counter, required when the wager has a new-player fee waiver
+    #[account(
+        mut,
+        seeds = [b"player_stats", wager.player1.as_ref()],
+        bump = player1_stats.bump
+    )]
+    pub player1_stats: Option<Account<'info, PlayerStats>>,
+    /// Player 2's game counter, required when the wager has a new-player fee waiver
+    #[account(
+        mut,
+        seeds = [b"player_stats", wager.player2.as_ref()],
+        bump = player2_stats.bump
+    )]
+    pub player2_stats: Option<Account<'info, PlayerStats>>,
+    /// CHECK: Instructions sysvar, read to find the ed25519 verification instruction
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DeclareWinnerWithConsent<'info> {
+    #[account(
+        mut,
+        seeds = [b"wager", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.bump
+    )]
+    pub wager: Account<'info, Wager>,
+    /// CHECK: Vault PDA for holding SOL deposits
+    #[account(
+        mut,
+        seeds = [b"vault", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.vault_bump
+    )]
+    pub vault: AccountInfo<'info>,
+    /// Receives the arbiter fee, if the wager has one
+    #[account(mut)]
+    pub arbiter: Signer<'info>,
+    #[account(address = wager.player1 @ ErrorCode::UnauthorizedPlayer)]
+    pub player1: Signer<'info>,
+    #[account(address = wager.player2 @ ErrorCode::UnauthorizedPlayer)]
+    pub player2: Signer<'info>,
+    /// CHECK: This is the winner account, validated against the winner's payout address
+    #[account(mut)]
+    pub winner_account: AccountInfo<'info>,
+    /// CHECK: This is the fee recipient account
+    #[account(mut, address = wager.fee_recipient @ ErrorCode::InvalidFeeRecipient)]
+    pub fee_recipient: AccountInfo<'info>,
+    /// CHECK: Secondary fee recipient, required when the wager has a fee split (validated in `pay_fee`)
+    #[account(mut)]
+    pub secondary_fee_recipient: Option<UncheckedAccount<'info>>,
+    /// Insurance pool receiving the insurance leg, required when the wager has one (validated in `pay_fee`)
+    #[account(mut)]
+    pub insurance_pool: Option<Account<'info, InsurancePool>>,
+    /// Compliance co-signer, required when the wager names an escrow authority (validated in `require_escrow_signature`)
+    pub escrow_authority: Option<Signer<'info>>,
+    /// The arbiter's settled-match counter, required when the wager has a fee waiver
+    #[account(
+        mut,
+        seeds = [b"arbiter", wager.arbiter.as_ref()],
+        bump = arbiter_stats.bump
+    )]
+    pub arbiter_stats: Option<Account<'info, ArbiterStats>>,
+    /// Player 1's game counter, required when the wager has a new-player fee waiver
+    #[account(
+        mut,
+        seeds = [b"player_stats", wager.player1.as_ref()],
+        bump = player1_stats.bump
+    )]
+    pub player1_stats: Option<Account<'info, PlayerStats>>,
+    /// Player 2's game counter, required when the wager has a new-player fee waiver
+    #[account(
+        mut,
+        seeds = [b"player_stats", wager.player2.as_ref()],
+        bump = player2_stats.bump
+    )]
+    pub player2_stats: Option<Account<'info, PlayerStats>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CommitResult<'info> {
+    #[account(
+        mut,
+        seeds = [b"wager", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.bump
+    )]
+    pub wager: Account<'info, Wager>,
+    pub arbiter: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SettleByBoth<'info> {
+    #[account(
+        mut,
+        seeds = [b"wager", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.bump
+    )]
+    pub wager: Account<'info, Wager>,
+    /// CHECK: Vault PDA for holding SOL deposits
+    #[account(
+        mut,
+        seeds = [b"vault", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.vault_bump
+    )]
+    pub vault: AccountInfo<'info>,
+    #[account(address = wager.player1 @ ErrorCode::UnauthorizedPlayer)]
+    pub player1: Signer<'info>,
+    #[account(address = wager.player2 @ ErrorCode::UnauthorizedPlayer)]
+    pub player2: Signer<'info>,
+    /// CHECK: This is the winner account, validated against the winner's payout address
+    #[account(mut)]
+    pub winner_account: AccountInfo<'info>,
+    /// CHECK: This is the fee recipient account
+    #[account(mut, address = wager.fee_recipient @ ErrorCode::InvalidFeeRecipient)]
+    pub fee_recipient: AccountInfo<'info>,
+    /// CHECK: Secondary fee recipient, required when the wager has a fee split (validated in `pay_fee`)
+    #[account(mut)]
+    pub secondary_fee_recipient: Option<UncheckedAccount<'info>>,
+    /// Insurance pool receiving the insurance leg, required when the wager has one (validated in `pay_fee`)
+    #[account(mut)]
+    pub insurance_pool: Option<Account<'info, InsurancePool>>,
+    /// Compliance co-signer, required when the wager names an escrow authority (validated in `require_escrow_signature`)
+    pub escrow_authority: Option<Signer<'info>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DepositAndConcede<'info> {
+    #[account(
+        mut,
+        seeds = [b"wager", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.bump
+    )]
+    pub wager: Account<'info, Wager>,
+    /// CHECK: Vault PDA for holding SOL deposits
+    #[account(
+        mut,
+        seeds = [b"vault", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.vault_bump
+    )]
+    pub vault: AccountInfo<'info>,
+    #[account(mut)]
+    pub player: Signer<'info>,
+    /// CHECK: The opponent's payout address, validated in `pay_out_winner`
+    #[account(mut)]
+    pub winner_account: AccountInfo<'info>,
+    /// CHECK: This is the fee recipient account
+    #[account(mut, address = wager.fee_recipient @ ErrorCode::InvalidFeeRecipient)]
+    pub fee_recipient: AccountInfo<'info>,
+    /// CHECK: Secondary fee recipient, required when the wager has a fee split (validated in `pay_fee`)
+    #[account(mut)]
+    pub secondary_fee_recipient: Option<UncheckedAccount<'info>>,
+    /// Insurance pool receiving the insurance leg, required when the wager has one (validated in `pay_fee`)
+    #[account(mut)]
+    pub insurance_pool: Option<Account<'info, InsurancePool>>,
+    /// Compliance co-signer, required when the wager names an escrow authority (validated in `require_escrow_signature`)
+    pub escrow_authority: Option<Signer<'info>>,
+    /// The conceder's open-wager cap, required when the wager has `cap_active_games`
+    #[account(
+        mut,
+        seeds = [b"activity", player.key().as_ref()],
+        bump = player_activity.bump
+    )]
+    pub player_activity: Option<Account<'info, PlayerActivity>>,
+    /// CHECK: Original payer, receiving the rent when the wager closes on settlement
+    #[account(mut, address = wager.payer @ ErrorCode::InvalidPayer)]
+    pub payer: Option<AccountInfo<'info>>,
+    /// The pair's stats, required when the wager closes on settlement
+    #[account(
+        mut,
+        seeds = [b"pair", wager.player1.as_ref(), wager.pair_seed.as_ref()],
+        bump = pair_stats.bump
+    )]
+    pub pair_stats: Option<Account<'info, PairStats>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Refund<'info> {
+    #[account(
+        mut,
+        seeds = [b"wager", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.bump
+    )]
+    pub wager: Account<'info, Wager>,
+    /// CHECK: Vault PDA for holding SOL deposits
+    #[account(
+        mut,
+        seeds = [b"vault", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.vault_bump
+    )]
+    pub vault: AccountInfo<'info>,
+    /// CHECK: Player 1 account for refund
+    #[account(mut, address = wager.player1 @ ErrorCode::UnauthorizedPlayer)]
+    pub player1: AccountInfo<'info>,
+    /// CHECK: Player 2 account for refund
+    #[account(mut, address = wager.player2 @ ErrorCode::UnauthorizedPlayer)]
+    pub player2: AccountInfo<'info>,
+    /// Audit record of this round's outcome (`["receipt", wager, series_round]`), written
+    /// when passed and kept after the wager is closed
+    #[account(
+        init,
+        payer = receipt_payer,
+        space = 8 + SettlementReceipt::INIT_SPACE,
+        seeds = [b"receipt", wager.key().as_ref(), &wager.series_round.to_le_bytes()],
+        bump
+    )]
+    pub receipt: Option<Account<'info, SettlementReceipt>>,
+    /// Pays for the receipt; required with it
+    #[account(mut)]
+    pub receipt_payer: Option<Signer<'info>>,
+    /// CHECK: Original payer, receiving the rent when the wager closes on settlement
+    #[account(mut, address = wager.payer @ ErrorCode::InvalidPayer)]
+    pub payer: Option<AccountInfo<'info>>,
+    /// The pair's stats, required when the wager closes on settlement
+    #[account(
+        mut,
+        seeds = [b"pair", wager.player1.as_ref(), wager.pair_seed.as_ref()],
+        bump = pair_stats.bump
+    )]
+    pub pair_stats: Option<Account<'info, PairStats>>,
+    /// CHECK: Fee recipient, sweeping its dust share when the wager closes on settlement
+    #[account(mut, address = wager.fee_recipient @ ErrorCode::InvalidFeeRecipient)]
+    pub fee_recipient: Option<AccountInfo<'info>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CrankRefund<'info> {
+    #[account(
+        mut,
+        seeds = [b"wager", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.bump
+    )]
+    pub wager: Account<'info, Wager>,
+    /// CHECK: Vault PDA for holding SOL deposits
+    #[account(
+        mut,
+        seeds = [b"vault", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.vault_bump
+    )]
+    pub vault: AccountInfo<'info>,
+    /// CHECK: Player 1 account for refund
+    #[account(mut, address = wager.player1 @ ErrorCode::UnauthorizedPlayer)]
+    pub player1: AccountInfo<'info>,
+    /// CHECK: Player 2 account for refund
+    #[account(mut, address = wager.player2 @ ErrorCode::UnauthorizedPlayer)]
+    pub player2: AccountInfo<'info>,
+    /// Whoever cranks the refund, receiving the keeper tip
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+    /// Audit record of this round's outcome (`["receipt", wager, series_round]`), written
+    /// when passed and kept after the wager is closed
+    #[account(
+        init,
+        payer = receipt_payer,
+        space = 8 + SettlementReceipt::INIT_SPACE,
+        seeds = [b"receipt", wager.key().as_ref(), &wager.series_round.to_le_bytes()],
+        bump
+    )]
+    pub receipt: Option<Account<'info, SettlementReceipt>>,
+    /// Pays for the receipt; required with it
+    #[account(mut)]
+    pub receipt_payer: Option<Signer<'info>>,
+    /// CHECK: Original payer, receiving the rent when the wager closes on settlement
+    #[account(mut, address = wager.payer @ ErrorCode::InvalidPayer)]
+    pub payer: Option<AccountInfo<'info>>,
+    /// The pair's stats, required when the wager closes on settlement
+    #[account(
+        mut,
+        seeds = [b"pair", wager.player1.as_ref(), wager.pair_seed.as_ref()],
+        bump = pair_stats.bump
+    )]
+    pub pair_stats: Option<Account<'info, PairStats>>,
+    /// CHECK: Fee recipient, sweeping its dust share when the wager closes on settlement
+    #[account(mut, address = wager.fee_recipient @ ErrorCode::InvalidFeeRecipient)]
+    pub fee_recipient: Option<AccountInfo<'info>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ArbiterVoid<'info> {
+    #[account(
+        mut,
+        seeds = [b"wager", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.bump
+    )]
+    pub wager: Account<'info, Wager>,
+    /// CHECK: Vault PDA for holding SOL deposits
+    #[account(
+        mut,
+        seeds = [b"vault", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.vault_bump
+    )]
+    pub vault: AccountInfo<'info>,
+    pub arbiter: Signer<'info>,
+    /// CHECK: Player 1 account for refund
+    #[account(mut, address = wager.player1 @ ErrorCode::UnauthorizedPlayer)]
+    pub player1: AccountInfo<'info>,
+    /// CHECK: Player 2 account for refund
+    #[account(mut, address = wager.player2 @ ErrorCode::UnauthorizedPlayer)]
+    pub player2: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MutualVoid<'info> {
+    #[account(
+        mut,
+        seeds = [b"wager", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.bump
+    )]
+    pub wager: Account<'info, Wager>,
+    /// CHECK: Vault PDA for holding SOL deposits
+    #[account(
+        mut,
+        seeds = [b"vault", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.vault_bump
+    )]
+    pub vault: AccountInfo<'info>,
+    /// CHECK: Player 1, who must sign (checked in the instruction) and is refunded
+    #[account(mut, address = wager.player1 @ ErrorCode::UnauthorizedPlayer)]
+    pub player1: AccountInfo<'info>,
+    /// CHECK: Player 2, who must sign (checked in the instruction) and is refunded
+    #[account(mut, address = wager.player2 @ ErrorCode::UnauthorizedPlayer)]
+    pub player2: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelWager<'info> {
+    #[account(
+        mut,
+        seeds = [b"wager", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.bump
+    )]
+    pub wager: Account<'info, Wager>,
+    /// CHECK: Vault PDA for holding SOL deposits
+    #[account(
+        mut,
+        seeds = [b"vault", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.vault_bump
+    )]
+    pub vault: AccountInfo<'info>,
+    /// CHECK: Player 1 account for refund
+    #[account(mut, address = wager.player1 @ ErrorCode::UnauthorizedPlayer)]
+    pub player1: AccountInfo<'info>,
+    /// CHECK: Player 2 account for refund; omitted for an open challenge nobody has
+    /// joined, whose player 2 is still `Pubkey::default()`
+    #[account(mut, address = wager.player2 @ ErrorCode::UnauthorizedPlayer)]
+    pub player2: Option<AccountInfo<'info>>,
+    /// Audit record of this round's outcome (`["receipt", wager, series_round]`), written
+    /// when passed and kept after the wager is closed
+    #[account(
+        init,
+        payer = receipt_payer,
+        space = 8 + SettlementReceipt::INIT_SPACE,
+        seeds = [b"receipt", wager.key().as_ref(), &wager.series_round.to_le_bytes()],
+        bump
+    )]
+    pub receipt: Option<Account<'info, SettlementReceipt>>,
+    /// Pays for the receipt; required with it
+    #[account(mut)]
+    pub receipt_payer: Option<Signer<'info>>,
+    /// CHECK: Original payer, receiving the rent when the wager closes on settlement
+    #[account(mut, address = wager.payer @ ErrorCode::InvalidPayer)]
+    pub payer: Option<AccountInfo<'info>>,
+    /// The pair's stats, required when the wager closes on settlement
+    #[account(
+        mut,
+        seeds = [b"pair", wager.player1.as_ref(), wager.pair_seed.as_ref()],
+        bump = pair_stats.bump
+    )]
+    pub pair_stats: Option<Account<'info, PairStats>>,
+    /// CHECK: Fee recipient, sweeping its dust share when the wager closes on settlement
+    #[account(mut, address = wager.fee_recipient @ ErrorCode::InvalidFeeRecipient)]
+    pub fee_recipient: Option<AccountInfo<'info>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimWinnings<'info> {
+    #[account(
+        mut,
+        seeds = [b"wager", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.bump
+    )]
+    pub wager: Account<'info, Wager>,
+    /// CHECK: Vault PDA for holding SOL deposits
+    #[account(
+        mut,
+        seeds = [b"vault", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.vault_bump
+    )]
+    pub vault: AccountInfo<'info>,
+    /// The winning player
+    pub player: Signer<'info>,
+    /// CHECK: This is the winner account, validated against the winner's payout address
+    #[account(mut)]
+    pub winner_account: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimAsTeam<'info> {
+    #[account(
+        mut,
+        seeds = [b"wager", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.bump
+    )]
+    pub wager: Account<'info, Wager>,
+    /// CHECK: Vault PDA for holding SOL deposits
+    #[account(
+        mut,
+        seeds = [b"vault", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.vault_bump
+    )]
+    pub vault: AccountInfo<'info>,
+    /// The winning player, claiming on behalf of the team
+    pub player: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SweepUnclaimed<'info> {
+    #[account(
+        mut,
+        seeds = [b"wager", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.bump
+    )]
+    pub wager: Account<'info, Wager>,
+    /// CHECK: Vault PDA for holding SOL deposits
+    #[account(
+        mut,
+        seeds = [b"vault", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.vault_bump
+    )]
+    pub vault: AccountInfo<'info>,
+    /// The fee recipient or the payer; receives the swept winnings
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseWager<'info> {
+    #[account(
+        mut,
+        seeds = [b"wager", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.bump,
+        close = payer
+    )]
+    pub wager: Account<'info, Wager>,
+    /// CHECK: Vault PDA for holding SOL deposits
+    #[account(
+        mut,
+        seeds = [b"vault", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.vault_bump
+    )]
+    pub vault: AccountInfo<'info>,
+    /// The pair's stats, counting the settlement; omitted for wagers created before the
+    /// pair had any
+    #[account(
+        mut,
+        seeds = [b"pair", wager.player1.as_ref(), wager.pair_seed.as_ref()],
+        bump = pair_stats.bump
+    )]
+    pub pair_stats: Option<Account<'info, PairStats>>,
+    /// CHECK: Original payer of the wager and vault rent
+    #[account(mut, address = wager.payer @ ErrorCode::InvalidPayer)]
+    pub payer: AccountInfo<'info>,
+    /// CHECK: This is the fee recipient account
+    #[account(mut, address = wager.fee_recipient @ ErrorCode::InvalidFeeRecipient)]
+    pub fee_recipient: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(nonce: u64)]
+pub struct Rematch<'info> {
+    /// The settled wager being rematched; left as it is
+    #[account(
+        seeds = [b"wager", prior_wager.player1.as_ref(), prior_wager.pda_seed.as_ref()],
+        bump = prior_wager.bump
+    )]
+    pub prior_wager: Account<'info, Wager>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Wager::INIT_SPACE,
+        seeds = [b"wager", prior_wager.player1.as_ref(), rematch_wager_seed(&prior_wager.key(), nonce).as_ref()],
+        bump
+    )]
+    pub wager: Account<'info, Wager>,
+    /// CHECK: Vault PDA for holding SOL deposits, as in `InitializeWager`
+    #[account(
+        init,
+        payer = payer,
+        space = 0,
+        owner = anchor_lang::system_program::ID,
+        seeds = [b"vault", prior_wager.player1.as_ref(), rematch_wager_seed(&prior_wager.key(), nonce).as_ref()],
+        bump
+    )]
+    pub vault: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"pair", prior_wager.player1.as_ref(), prior_wager.pair_seed.as_ref()],
+        bump = pair_stats.bump
+    )]
+    pub pair_stats: Account<'info, PairStats>,
+    /// Either player, or the payer of the prior wager; pays the new PDAs' rent
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SettleAndClose<'info> {
+    #[account(
+        mut,
+        seeds = [b"wager", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.bump,
+        close = payer
+    )]
+    pub wager: Account<'info, Wager>,
+    /// CHECK: Vault PDA for holding SOL deposits
+    #[account(
+        mut,
+        seeds = [b"vault", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.vault_bump
+    )]
+    pub vault: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"pair", wager.player1.as_ref(), wager.pair_seed.as_ref()],
+        bump = pair_stats.bump
+    )]
+    pub pair_stats: Account<'info, PairStats>,
+    /// Receives the arbiter fee, if the wager has one
+    #[account(mut)]
+    pub arbiter: Signer<'info>,
+    /// CHECK: This is the winner account, validated against the winner's payout address
+    #[account(mut)]
+    pub winner_account: AccountInfo<'info>,
+    /// CHECK: This is the fee recipient account
+    #[account(mut, address = wager.fee_recipient @ ErrorCode::InvalidFeeRecipient)]
+    pub fee_recipient: AccountInfo<'info>,
+    /// CHECK: Secondary fee recipient, required when the wager has a fee split (validated in `pay_fee`)
+    #[account(mut)]
+    pub secondary_fee_recipient: Option<UncheckedAccount<'info>>,
+    /// Insurance pool receiving the insurance leg, required when the wager has one (validated in `pay_fee`)
+    #[account(mut)]
+    pub insurance_pool: Option<Account<'info, InsurancePool>>,
+    /// Compliance co-signer, required when the wager names an escrow authority (validated in `require_escrow_signature`)
+    pub escrow_authority: Option<Signer<'info>>,
+    /// The arbiter's settled-match counter, required when the wager has a fee waiver
+    #[account(
+        mut,
+        seeds = [b"arbiter", wager.arbiter.as_ref()],
+        bump = arbiter_stats.bump
+    )]
+    pub arbiter_stats: Option<Account<'info, ArbiterStats>>,
+    /// Player 1's game counter, required when the wager has a new-player fee waiver
+    #[account(
+        mut,
+        seeds = [b"player_stats", wager.player1.as_ref()],
+        bump = player1_stats.bump
+    )]
+    pub player1_stats: Option<Account<'info, PlayerStats>>,
+    /// Player 2's game counter, required when the wager has a new-player fee waiver
+    #[account(
+        mut,
+        seeds = [b"player_stats", wager.player2.as_ref()],
+        bump = player2_stats.bump
+    )]
+    pub player2_stats: Option<Account<'info, PlayerStats>>,
+    /// CHECK: Original payer of the wager and vault rent
+    #[account(mut, address = wager.payer @ ErrorCode::InvalidPayer)]
+    pub payer: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReclaimAbandoned<'info> {
+    #[account(
+        mut,
+        seeds = [b"wager", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.bump,
+        close = payer
+    )]
+    pub wager: Account<'info, Wager>,
+    /// CHECK: Vault PDA for holding SOL deposits
+    #[account(
+        mut,
+        seeds = [b"vault", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.vault_bump
+    )]
+    pub vault: AccountInfo<'info>,
+    /// CHECK: Original payer of the wager and vault rent
+    #[account(mut, address = wager.payer @ ErrorCode::InvalidPayer)]
+    pub payer: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReclaimInitCost<'info> {
+    #[account(
+        mut,
+        seeds = [b"wager", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.bump,
+        close = payer
+    )]
+    pub wager: Account<'info, Wager>,
+    /// CHECK: Vault PDA for holding SOL deposits
+    #[account(
+        mut,
+        seeds = [b"vault", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.vault_bump
+    )]
+    pub vault: AccountInfo<'info>,
+    /// Original payer of the wager and vault rent
+    #[account(mut, address = wager.payer @ ErrorCode::InvalidPayer)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReopenWager<'info> {
+    #[account(
+        mut,
+        seeds = [b"wager", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.bump
+    )]
+    pub wager: Account<'info, Wager>,
+    /// CHECK: Vault PDA for holding SOL deposits
+    #[account(
+        seeds = [b"vault", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.vault_bump
+    )]
+    pub vault: AccountInfo<'info>,
+    /// The player who had not deposited when the wager was cancelled
+    pub player: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ReadyUp<'info> {
+    #[account(
+        mut,
+        seeds = [b"wager", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.bump
+    )]
+    pub wager: Account<'info, Wager>,
+    /// A player readying up, or anyone once the ready timeout has passed
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateArbiter<'info> {
+    #[account(
+        mut,
+        seeds = [b"wager", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.bump
+    )]
+    pub wager: Account<'info, Wager>,
+    /// Current arbiter, or one of the players
+    pub authority: Signer<'info>,
+    /// The other player, when the players change the arbiter jointly
+    pub co_signer: Option<Signer<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateFeeRecipient<'info> {
+    #[account(
+        mut,
+        seeds = [b"wager", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.bump
+    )]
+    pub wager: Account<'info, Wager>,
+    /// The current fee recipient
+    pub fee_recipient: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetSettlementsFrozen<'info> {
+    #[account(
+        mut,
+        seeds = [b"wager", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.bump
+    )]
+    pub wager: Account<'info, Wager>,
+    pub arbiter: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateWagerAmount<'info> {
+    #[account(
+        mut,
+        seeds = [b"wager", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.bump
+    )]
+    pub wager: Account<'info, Wager>,
+    /// The payer, or one of the players
+    pub authority: Signer<'info>,
+    /// The other player, when the players change the amount jointly
+    pub co_signer: Option<Signer<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct SetPayoutAddress<'info> {
+    #[account(
+        mut,
+        seeds = [b"wager", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.bump
+    )]
+    pub wager: Account<'info, Wager>,
+    pub player: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct VerifyProgram {}
+
+#[derive(Accounts)]
+pub struct ReadWager<'info> {
+    #[account(
+        seeds = [b"wager", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.bump
+    )]
+    pub wager: Account<'info, Wager>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeSidePool<'info> {
+    #[account(
+        mut,
+        seeds = [b"wager", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.bump
+    )]
     pub wager: Account<'info, Wager>,
-    /// CHECK: Vault PDA for holding SOL deposits (no data, just SOL storage)
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + SidePool::INIT_SPACE,
+        seeds = [b"side_pool", wager.key().as_ref()],
+        bump
+    )]
+    pub side_pool: Account<'info, SidePool>,
+    /// CHECK: System-owned vault holding the stakes backing player 1
     #[account(
         init,
         payer = payer,
         space = 0,
-        seeds = [b"vault", player1.as_ref(), player2.as_ref()],
+        owner = anchor_lang::system_program::ID,
+        seeds = [b"side_vault", wager.key().as_ref(), &[1u8]],
         bump
     )]
-    pub vault: AccountInfo<'info>,
+    pub side_vault_p1: AccountInfo<'info>,
+    /// CHECK: System-owned vault holding the stakes backing player 2
+    #[account(
+        init,
+        payer = payer,
+        space = 0,
+        owner = anchor_lang::system_program::ID,
+        seeds = [b"side_vault", wager.key().as_ref(), &[2u8]],
+        bump
+    )]
+    pub side_vault_p2: AccountInfo<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(player: u8)]
+pub struct PlaceSideBet<'info> {
+    #[account(
+        seeds = [b"wager", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.bump
+    )]
+    pub wager: Account<'info, Wager>,
+    #[account(
+        mut,
+        seeds = [b"side_pool", wager.key().as_ref()],
+        bump = side_pool.bump
+    )]
+    pub side_pool: Account<'info, SidePool>,
+    /// CHECK: Side vault of the backed player
+    #[account(
+        mut,
+        seeds = [b"side_vault", wager.key().as_ref(), &[player]],
+        bump
+    )]
+    pub side_vault: AccountInfo<'info>,
+    #[account(
+        init,
+        payer = backer,
+        space = 8 + SideBet::INIT_SPACE,
+        seeds = [b"side_bet", wager.key().as_ref(), backer.key().as_ref()],
+        bump
+    )]
+    pub side_bet: Account<'info, SideBet>,
+    #[account(mut)]
+    pub backer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SettleSidePool<'info> {
+    #[account(
+        mut,
+        seeds = [b"wager", wager.player1.as_ref(), wager.pda_seed.as_ref()],
+        bump = wager.bump
+    )]
+    pub wager: Account<'info, Wager>,
+    #[account(
+        mut,
+        seeds = [b"side_pool", wager.key().as_ref()],
+        bump = side_pool.bump
+    )]
+    pub side_pool: Account<'info, SidePool>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimSideBet<'info> {
+    #[account(
+        seeds = [b"side_pool", side_pool.wager.as_ref()],
+        bump = side_pool.bump
+    )]
+    pub side_pool: Account<'info, SidePool>,
+    /// CHECK: System-owned vault holding the stakes backing player 1
+    #[account(
+        mut,
+        seeds = [b"side_vault", side_pool.wager.as_ref(), &[1u8]],
+        bump = side_pool.vault_bumps[0]
+    )]
+    pub side_vault_p1: AccountInfo<'info>,
+    /// CHECK: System-owned vault holding the stakes backing player 2
+    #[account(
+        mut,
+        seeds = [b"side_vault", side_pool.wager.as_ref(), &[2u8]],
+        bump = side_pool.vault_bumps[1]
+    )]
+    pub side_vault_p2: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"side_bet", side_pool.wager.as_ref(), backer.key().as_ref()],
+        bump = side_bet.bump,
+        has_one = backer,
+        close = backer
+    )]
+    pub side_bet: Account<'info, SideBet>,
+    #[account(mut)]
+    pub backer: Signer<'info>,
+    /// CHECK: The wager's fee recipient, paid the fee on side-bet winnings
+    #[account(mut, address = side_pool.fee_recipient @ ErrorCode::InvalidFeeRecipient)]
+    pub fee_recipient: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetPairCooldown<'info> {
+    #[account(
+        mut,
+        seeds = [b"pair", player1.key().as_ref(), player2.key().as_ref()],
+        bump = pair_stats.bump
+    )]
+    pub pair_stats: Account<'info, PairStats>,
+    pub player1: Signer<'info>,
+    pub player2: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializePlayerActivity<'info> {
+    #[account(
+        init,
+        payer = player,
+        space = 8 + PlayerActivity::INIT_SPACE,
+        seeds = [b"activity", player.key().as_ref()],
+        bump
+    )]
+    pub player_activity: Account<'info, PlayerActivity>,
+    #[account(mut)]
+    pub player: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseActiveGame<'info> {
+    #[account(
+        mut,
+        seeds = [b"activity", player_activity.player.as_ref()],
+        bump = player_activity.bump
+    )]
+    pub player_activity: Account<'info, PlayerActivity>,
+    /// CHECK: Read raw because a closed wager no longer deserializes; an existing one must
+    /// be a settled `Wager` owned by the program (checked in `release_active_game`)
+    pub wager: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateWager<'info> {
+    /// CHECK: Read raw because an older layout does not deserialize as `Wager`; owner is
+    /// checked here and the discriminator in `migrate_wager`, which also grows the account
+    #[account(mut, owner = crate::ID)]
+    pub wager: UncheckedAccount<'info>,
     #[account(mut)]
     pub payer: Signer<'info>,
     pub system_program: Program<'info, System>,
 }
 
-#[derive(Accounts)]
-pub struct DepositPlayer1<'info> {
-    #[account(
-        mut,
-        seeds = [b"wager", wager.player1.as_ref(), wager.player2.as_ref()],
-        bump = wager.bump
-    )]
-    pub wager: Account<'info, Wager>,
-    /// CHECK: Vault PDA for holding SOL deposits
-    #[account(
-        mut,
-        seeds = [b"vault", wager.player1.as_ref(), wager.player2.as_ref()],
-        bump = wager.vault_bump
-    )]
-    pub vault: AccountInfo<'info>,
-    #[account(mut)]
-    pub player1: Signer<'info>,
-    pub system_program: Program<'info, System>,
+#[account]
+#[derive(InitSpace, Default)]
+pub struct Wager {
+    pub player1: Pubkey,
+    pub player2: Pubkey,
+    pub arbiter: Pubkey,
+    pub fee_recipient: Pubkey,
+    pub wager_amount: u64,
+    /// Deposit flags of the v1 layout, read only by `migrate_wager`; `status` has
+    /// replaced them
+    pub legacy_player1_deposited: bool,
+    pub legacy_player2_deposited: bool,
+    pub creation_time: i64,
+    pub start_time: i64,
+    pub winner: Option<u8>,
+    /// Settlement flag of the v1 layout, read only by `migrate_wager`
+    pub legacy_is_settled: bool,
+    pub bump: u8,
+    pub vault_bump: u8,
+    pub initialization_cost: u64,
+    pub payer: Pubkey,
+    pub payer_dust_bps: u16,
+    pub deduct_init_cost_from_pool: bool,
+    pub scheduled_start: i64,
+    pub pay_yield_to_winner: bool,
+    pub yield_paid: u64,
+    pub player1_payout: Option<Pubkey>,
+    pub player2_payout: Option<Pubkey>,
+    pub fast_threshold_seconds: i64,
+    pub fast_rebate_bps: u16,
+    pub tournament_id: Option<[u8; 16]>,
+    #[max_len(MAX_ARBITER_ROTATION)]
+    pub arbiter_rotation: Vec<Pubkey>,
+    pub series_round: u64,
+    /// When the first player deposited (0 until then); starts the opponent's deposit window
+    pub first_deposit_time: i64,
+    /// Lamports above rent already in the vault address at init; excluded from the pool and yield
+    pub vault_prefund: u64,
+    pub fee_split: Option<FeeSplit>,
+    /// `sha256(winner || salt)` committed by the arbiter ahead of `reveal_result`
+    pub result_commitment: Option<[u8; 32]>,
+    /// Seconds the winner has to claim held winnings before they can be swept (0 = paid immediately)
+    pub claim_window_seconds: i64,
+    /// When the winner was declared (0 until then); starts the claim window
+    pub declared_time: i64,
+    /// Winner's share still held in the vault awaiting `claim_winnings`
+    pub unclaimed_winnings: u64,
+    /// Seconds that must pass after the match starts before a winner can be declared
+    pub min_match_seconds: i64,
+    pub fee_waiver: Option<FeeWaiver>,
+    /// Off-chain match ID or lobby code this wager belongs to
+    pub game_ref: [u8; 32],
+    pub insurance: Option<InsuranceLeg>,
+    /// Seconds between the second deposit and the match timer starting
+    pub start_delay_seconds: i64,
+    /// Compliance co-signer required on every settlement that pays a winner
+    pub escrow_authority: Option<Pubkey>,
+    /// Layout version written at initialization (see `migrate_wager`)
+    pub version: u8,
+    /// A side pool is open on this wager; it must be settled before the wager is closed
+    pub has_side_pool: bool,
+    /// Lifecycle stage, for clients listing open games
+    pub status: WagerStatus,
+    /// Fee waiver for games where either player has few games recorded in `PlayerStats`
+    pub new_player_waiver: Option<FeeWaiver>,
+    /// Arbiter's fee (basis points of the pool), paid from the winner's share on arbiter
+    /// declarations
+    pub arbiter_fee_bps: u16,
+    /// The fee is rounded down (winner keeps the remainder) rather than up
+    pub round_fee_down: bool,
+    /// After this time an unsettled wager can be voided with `void_expired` (0 = never)
+    pub expiry_time: i64,
+    /// Second seed of the wager, vault and pair PDAs: player 2's key, or for an open
+    /// challenge the nonce-derived `open_wager_seed` (player 2 is set only on joining)
+    pub pda_seed: Pubkey,
+    /// Times `update_arbiter` has replaced the arbiter (at most `MAX_ARBITER_CHANGES`)
+    pub arbiter_changes: u8,
+    /// Player 2 is the house (also the fee recipient), which must deposit before player 1
+    pub house_wager: bool,
+    /// Declarations must be the only instruction touching the wager, vault or winner account
+    pub strict_isolation: bool,
+    /// Settlements close the wager and sweep the vault themselves
+    pub close_on_settle: bool,
+    /// Final scores of players 1 and 2, when settled through `declare_result`
+    pub scores: Option<[u32; 2]>,
+    /// Cut of the refunded pool paid to whoever cranks an expired match's refund
+    pub keeper_reward_bps: u16,
+    /// When the wager was last cancelled (0 if never), opening the `reopen_wager` window
+    pub cancelled_time: i64,
+    /// The fee recipient may never be one of the players
+    pub strict_fee_recipient: bool,
+    /// Authority of the `FeeVault` the fees accumulate in (then also the fee recipient);
+    /// `None` when fees are paid directly to the fee recipient
+    pub fee_vault_authority: Option<Pubkey>,
+    /// The match timer only starts once both players have readied up with `ready_up`
+    pub require_ready: bool,
+    pub player1_ready: bool,
+    pub player2_ready: bool,
+    /// When the second deposit landed (0 until then), starting the ready timeout
+    pub deposits_completed_time: i64,
+    /// Second seed of the pair's `PairStats`: `pda_seed`, except on a rematch, whose
+    /// fresh PDAs keep counting against the original pair
+    pub pair_seed: Pubkey,
+    /// Deposits must pass the player's `PlayerActivity`, counting against their cap
+    pub cap_active_games: bool,
+    /// Set by the arbiter to halt every payout of a result; refunds and voids still work
+    pub settlements_frozen: bool,
+}
+
+impl Wager {
+    /// Portion of the initialization cost charged to the players' pool
+    /// (zero when the payer absorbs the rent and recovers it on close)
+    pub fn pool_init_cost(&self) -> u64 {
+        if self.deduct_init_cost_from_pool {
+            self.initialization_cost
+        } else {
+            0
+        }
+    }
+
+    /// Packed lifecycle summary: bit 0 = player 1 deposited, bit 1 = player 2 deposited,
+    /// bit 2 = settled, bits 3-4 = winner (0 = none, 1 = player 1, 2 = player 2)
+    pub fn status_byte(&self) -> u8 {
+        let mut status = 0;
+        if self.player1_deposited() {
+            status |= STATUS_PLAYER1_DEPOSITED;
+        }
+        if self.player2_deposited() {
+            status |= STATUS_PLAYER2_DEPOSITED;
+        }
+        if self.is_settled() {
+            status |= STATUS_SETTLED;
+        }
+        status | (self.winner.unwrap_or(0) << STATUS_WINNER_SHIFT)
+    }
+
+    /// Instructions valid at `now`, as `ACTION_*` bits. Reflects the wager's state and
+    /// timing only; signer checks are left to the instructions themselves.
+    pub fn available_actions(&self, now: i64) -> u16 {
+        let mut actions = 0;
+        let both_deposited = self.player1_deposited() && self.player2_deposited();
+        
+        if !self.is_settled() {
+            if !self.player1_deposited() && (!self.house_wager || self.player2_deposited()) {
+                actions |= ACTION_DEPOSIT_PLAYER1;
+            }
+            if self.player2 == Pubkey::default() {
+                actions |= ACTION_JOIN_WAGER;
+            } else if !self.player2_deposited() {
+                actions |= ACTION_DEPOSIT_PLAYER2;
+            }
+            // Voiding needs both deposits but, unlike a declaration, no started match
+            if both_deposited {
+                actions |= ACTION_ARBITER_VOID | ACTION_MUTUAL_VOID;
+            }
+            if both_deposited && self.start_time != 0 {
+                let declarable = !self.settlements_frozen
+                    && match_elapsed(self.start_time, now, CLOCK_SKEW_TOLERANCE_SECONDS)
+                        .is_some_and(|elapsed| elapsed >= self.min_match_seconds && elapsed <= TIMEOUT_SECONDS);
+                if declarable && self.result_commitment.is_none() {
+                    actions |= ACTION_DECLARE_WINNER;
+                }
+                if declarable && self.result_commitment.is_some() {
+                    actions |= ACTION_REVEAL_RESULT;
+                }
+                if self.match_timed_out(now) {
+                    actions |= ACTION_REFUND;
+                }
+            }
+            if !both_deposited && self.deposit_window_expired(now) {
+                actions |= ACTION_CANCEL_WAGER;
+                if !self.player1_deposited() && !self.player2_deposited() {
+                    actions |= ACTION_RECLAIM_ABANDONED;
+                }
+            }
+            if self.is_expired(now) {
+                actions |= ACTION_VOID_EXPIRED;
+            }
+        } else if self.unclaimed_winnings > 0 {
+            actions |= ACTION_CLAIM_WINNINGS;
+            if self.claim_window_expired(now) {
+                actions |= ACTION_SWEEP_UNCLAIMED;
+            }
+        } else {
+            actions |= ACTION_CLOSE_WAGER | ACTION_REMATCH;
+        }
+        actions
+    }
+
+    /// Whether `expiry_time` has passed, so `void_expired` may call off the wager
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expiry_time != 0 && now > self.expiry_time
+    }
+
+    /// Seat (1 or 2) of `key` if it is one of the players. An open challenge's empty seat
+    /// (the default key) matches nobody.
+    pub fn player_number(&self, key: &Pubkey) -> Option<u8> {
+        if *key == Pubkey::default() {
+            None
+        } else if *key == self.player1 {
+            Some(1)
+        } else if *key == self.player2 {
+            Some(2)
+        } else {
+            None
+        }
+    }
+
+    /// Whether `declare_winners_batch` can settle this wager. A batch passes none of
+    /// `declare_winner`'s optional accounts (escrow co-signer, fee waiver stats, insurance
+    /// pool, secondary fee recipient, instructions sysvar, close accounts), so wagers that
+    /// would need one are settled on their own.
+    pub fn is_batchable(&self) -> bool {
+        self.escrow_authority.is_none()
+            && self.fee_waiver.is_none()
+            && self.new_player_waiver.is_none()
+            && self.insurance.is_none()
+            && self.fee_split.is_none()
+            && !self.strict_isolation
+            && !self.close_on_settle
+    }
+
+    /// Whether `first` and `second` are the two players, in either order
+    pub fn are_both_players(&self, first: &Pubkey, second: Option<&Pubkey>) -> bool {
+        match (self.player_number(first), second.and_then(|key| self.player_number(key))) {
+            (Some(a), Some(b)) => a != b,
+            _ => false,
+        }
+    }
+
+    /// Whether `key` takes part in this wager as a player, the arbiter or the fee recipient
+    pub fn is_participant(&self, key: &Pubkey) -> bool {
+        self.player_number(key).is_some() || *key == self.arbiter || *key == self.fee_recipient
+    }
+
+    /// Account that receives winnings for player 1 or 2 (registered override or the player's key)
+    pub fn payout_address(&self, winner: u8) -> Pubkey {
+        if winner == 1 {
+            self.player1_payout.unwrap_or(self.player1)
+        } else {
+            self.player2_payout.unwrap_or(self.player2)
+        }
+    }
+
+    /// The wager's vault PDA, from its stored (canonical) bump: one hash, where
+    /// `find_program_address` would search for the bump again
+    pub fn vault_address(&self) -> Result<Pubkey> {
+        Pubkey::create_program_address(
+            &[b"vault", self.player1.as_ref(), self.pda_seed.as_ref(), &[self.vault_bump]],
+            &crate::ID,
+        )
+        .map_err(|_| error!(ErrorCode::InvalidVaultBump))
+    }
+
+    /// Stakes of player 1 and player 2. Both seats stake `wager_amount`; pool arithmetic
+    /// goes through this rather than assuming the pool is twice one stake.
+    pub fn stakes(&self) -> [u64; 2] {
+        [self.wager_amount, self.wager_amount]
+    }
+
+    /// Both stakes together: the pool of a fully funded match
+    pub fn total_pool(&self) -> Result<u64> {
+        sum_stakes(self.stakes())
+    }
+
+    /// Stakes actually deposited into the vault so far
+    pub fn principal(&self) -> Result<u64> {
+        let [stake1, stake2] = self.stakes();
+        let deposited = |stake: u64, deposited: bool| if deposited { stake } else { 0 };
+        sum_stakes([
+            deposited(stake1, self.player1_deposited()),
+            deposited(stake2, self.player2_deposited()),
+        ])
+    }
+
+    /// Winner's share and fee for the staked principal, after deducting the
+    /// initialization cost from the pool (if configured)
+    pub fn winnings_split(&self) -> Result<(u64, u64)> {
+        Ok(self.split_pool(self.principal()?))
+    }
+
+    /// Arbiter's fee for a given principal: `arbiter_fee_bps` of the pool after the
+    /// initialization cost, rounded down
+    pub fn arbiter_fee(&self, principal: u64) -> u64 {
+        let distributable_pool = principal.checked_sub(self.pool_init_cost()).unwrap();
+        distributable_pool.checked_mul(self.arbiter_fee_bps as u64).unwrap().checked_div(BPS_DENOMINATOR).unwrap()
+    }
+
+    /// Winner's share and fee for a given principal, after deducting the
+    /// initialization cost from the pool (if configured). With `round_fee_down` the fee is
+    /// computed first and rounded down, so the winner keeps any truncation remainder;
+    /// otherwise the winner's share is rounded down and the fee recipient keeps it.
+    pub fn split_pool(&self, principal: u64) -> (u64, u64) {
+        let distributable_pool = principal.checked_sub(self.pool_init_cost()).unwrap();
+        if self.round_fee_down {
+            let fee_amount = distributable_pool.checked_mul(FEE_PERCENTAGE).unwrap().checked_div(100).unwrap();
+            let winner_amount = distributable_pool.checked_sub(fee_amount).unwrap();
+            (winner_amount, fee_amount)
+        } else {
+            let winner_amount = distributable_pool.checked_mul(WINNER_PERCENTAGE).unwrap().checked_div(100).unwrap();
+            let fee_amount = distributable_pool.checked_sub(winner_amount).unwrap();
+            (winner_amount, fee_amount)
+        }
+    }
+
+    /// Each player's net change (payout minus deposit) when `winner` is paid
+    /// `winner_amount`; a player who never deposited only gains their payout
+    pub fn net_changes(&self, winner: u8, winner_amount: u64) -> (i64, i64) {
+        let [stake1, stake2] = self.stakes();
+        let deposit = |stake: u64, deposited: bool| if deposited { stake } else { 0 };
+        let payout = |player: u8| if player == winner { winner_amount } else { 0 };
+        (
+            net_change(payout(1), deposit(stake1, self.player1_deposited())),
+            net_change(payout(2), deposit(stake2, self.player2_deposited())),
+        )
+    }
+
+    /// Lifecycle stage implied by the v1 deposit and settlement flags, for wagers written
+    /// before the status existed. Exact for every stage except a settled wager without a
+    /// winner: with both deposits it reads as refunded, otherwise as cancelled.
+    pub fn legacy_status(&self) -> WagerStatus {
+        let seats = (self.legacy_player1_deposited, self.legacy_player2_deposited);
+        match (self.legacy_is_settled, self.winner, seats) {
+            (true, Some(_), _) => WagerStatus::Settled,
+            (true, None, (true, true)) => WagerStatus::Refunded,
+            (true, None, (true, false)) => WagerStatus::CancelledPlayer1Refunded,
+            (true, None, (false, true)) => WagerStatus::CancelledPlayer2Refunded,
+            (true, None, (false, false)) => WagerStatus::CancelledUnfunded,
+            (false, _, (true, true)) => WagerStatus::Active,
+            (false, _, (true, false)) => WagerStatus::WaitingPlayer2,
+            (false, _, (false, true)) => WagerStatus::WaitingPlayer1,
+            (false, _, (false, false)) => WagerStatus::Created,
+        }
+    }
+    
+    /// Player 1 has deposited, as implied by the status
+    pub fn player1_deposited(&self) -> bool {
+        self.status.player1_deposited()
+    }
+    
+    /// Player 2 has deposited, as implied by the status
+    pub fn player2_deposited(&self) -> bool {
+        self.status.player2_deposited()
+    }
+    
+    /// The wager is settled, refunded or cancelled, as implied by the status
+    pub fn is_settled(&self) -> bool {
+        self.status.is_settled()
+    }
+    
+    /// Amount returned to each player when a fully funded game is refunded or voided
+    pub fn refund_amount_each(&self) -> Result<u64> {
+        let distributable_pool = self
+            .total_pool()?
+            .checked_sub(self.pool_init_cost())
+            .ok_or(ErrorCode::PoolOverflow)?;
+        Ok(distributable_pool / 2)
+    }
+
+    /// Flat tip each player pays the keeper of a `crank_refund`: half of
+    /// `KEEPER_TIP_LAMPORTS`, but never more than the platform fee on their refund
+    pub fn keeper_tip_each(&self) -> Result<u64> {
+        let fee_each = self
+            .refund_amount_each()?
+            .checked_mul(FEE_PERCENTAGE)
+            .ok_or(ErrorCode::PoolOverflow)?;
+        Ok((fee_each / 100).min(KEEPER_TIP_LAMPORTS / 2))
+    }
+
+    /// Part of each player's refund that goes to the keeper of a `crank_refund`
+    pub fn keeper_reward_each(&self) -> Result<u64> {
+        let reward = self
+            .refund_amount_each()?
+            .checked_mul(self.keeper_reward_bps as u64)
+            .ok_or(ErrorCode::PoolOverflow)?;
+        Ok(reward / BPS_DENOMINATOR)
+    }
+
+    /// Timestamp the match timer starts from once both players have deposited: after
+    /// the start delay, or at the scheduled start if that is later
+    pub fn match_start_time(&self, now: i64) -> i64 {
+        self.scheduled_start.max(now.saturating_add(self.start_delay_seconds))
+    }
+
+    /// Timestamp the deposit timeout is measured from: the first deposit if one has
+    /// landed (so a late first deposit still leaves the opponent a full window), else
+    /// creation. Scheduled matches accept deposits until the timeout after their start.
+    pub fn deposit_window_start(&self) -> i64 {
+        let opened = if self.first_deposit_time != 0 {
+            self.first_deposit_time
+        } else {
+            self.creation_time
+        };
+        opened.max(self.scheduled_start)
+    }
+
+    /// The deposit window has closed at `now`, so an incomplete wager can be cancelled
+    pub fn deposit_window_expired(&self, now: i64) -> bool {
+        deposit_time_remaining(self.deposit_window_start(), now) < 0
+    }
+
+    /// The match timeout has passed at `now`, so the arbiter can no longer declare and the
+    /// stakes can be refunded. Only meaningful once `start_time` is set.
+    pub fn match_timed_out(&self, now: i64) -> bool {
+        match_time_remaining(self.start_time, now, TIMEOUT_SECONDS) < 0
+    }
+
+    /// The claim window for held winnings has closed at `now`, so they can be swept
+    pub fn claim_window_expired(&self, now: i64) -> bool {
+        match_time_remaining(self.declared_time, now, self.claim_window_seconds) < 0
+    }
+}
+
+/// Seconds left until `start + timeout`. Zero means `now` is exactly at the deadline
+/// (still inside the window); negative means the window has expired.
+pub fn match_time_remaining(start: i64, now: i64, timeout: i64) -> i64 {
+    start.saturating_add(timeout).saturating_sub(now)
+}
+
+/// Seconds elapsed since `start`, never negative. A clock reading behind `start` (skew, or
+/// a scheduled start still ahead) counts as `start` if within `skew_tolerance`, and is
+/// otherwise `None` so callers reject it instead of passing a `<= timeout` check.
+pub fn match_elapsed(start: i64, now: i64, skew_tolerance: i64) -> Option<i64> {
+    if now >= start {
+        Some(now.saturating_sub(start))
+    } else if start.saturating_sub(now) <= skew_tolerance {
+        Some(0)
+    } else {
+        None
+    }
+}
+
+/// Net lamport change for a player who deposited `deposited` and was paid `payout`
+pub fn net_change(payout: u64, deposited: u64) -> i64 {
+    (payout as i128 - deposited as i128) as i64
+}
+
+/// Seconds left in the deposit window opened at `window_start`, same sign convention
+/// as `match_time_remaining`
+pub fn deposit_time_remaining(window_start: i64, now: i64) -> i64 {
+    match_time_remaining(window_start, now, DEPOSIT_TIMEOUT_SECONDS)
+}
+
+/// Sum of the per-seat stakes, failing instead of wrapping when it overflows
+fn sum_stakes(stakes: [u64; 2]) -> Result<u64> {
+    stakes[0].checked_add(stakes[1]).ok_or_else(|| error!(ErrorCode::PoolOverflow))
+}
+
+/// A stake charged with the initialization cost must exceed it. The pool (two stakes) then
+/// covers the PDA rent, and so does a lone depositor's stake, which bears the whole cost
+/// when the opponent never shows and the wager is cancelled.
+fn require_stake_covers_init_cost(wager_amount: u64, init_cost: u64) -> Result<()> {
+    require!(wager_amount > init_cost, ErrorCode::WagerTooSmallForRent);
+    Ok(())
+}
+
+/// Ensure a winner would actually be paid from `principal`: a tiny pool must still cover
+/// the initialization cost charged to it, and the fee must never take the whole remainder
+fn require_positive_winner_payout(wager: &Wager, principal: u64) -> Result<()> {
+    require!(principal > wager.pool_init_cost(), ErrorCode::ZeroWinnerPayout);
+    let (winner_amount, _) = wager.split_pool(principal);
+    require!(winner_amount > 0, ErrorCode::ZeroWinnerPayout);
+    Ok(())
+}
+
+/// Winnings must leave the destination rent exempt for its data size. A fresh payout
+/// address (no lamports yet) receiving less than the rent-exempt minimum could not hold
+/// them, so the settlement fails cleanly instead of in the runtime's rent check.
+fn require_viable_payout(destination: &AccountInfo, amount: u64) -> Result<()> {
+    let balance_after = destination.lamports().saturating_add(amount);
+    require!(
+        balance_after >= Rent::get()?.minimum_balance(destination.data_len()),
+        ErrorCode::WinnerAccountNotViable
+    );
+    Ok(())
+}
+
+/// Split `amount` by basis-point `shares` (1 to `MAX_TEAM_MEMBERS` of them, each positive,
+/// summing to 10000). Shares are rounded down and the remainder goes to the first member.
+fn team_payouts(amount: u64, shares: &[u16]) -> Result<Vec<u64>> {
+    require!(
+        !shares.is_empty() && shares.len() <= MAX_TEAM_MEMBERS,
+        ErrorCode::InvalidTeamShares
+    );
+    require!(shares.iter().all(|&share| share > 0), ErrorCode::InvalidTeamShares);
+    let total_bps: u64 = shares.iter().map(|&share| share as u64).sum();
+    require!(total_bps == BPS_DENOMINATOR, ErrorCode::InvalidTeamShares);
+    
+    let mut payouts: Vec<u64> = shares
+        .iter()
+        .map(|&share| (amount as u128 * share as u128 / BPS_DENOMINATOR as u128) as u64)
+        .collect();
+    let remainder = amount.checked_sub(payouts.iter().sum()).unwrap();
+    payouts[0] = payouts[0].checked_add(remainder).unwrap();
+    Ok(payouts)
+}
+
+/// Post-condition for deposits: the vault grew by exactly the stake, so a future change
+/// to the transfer path cannot silently desync the deposit flags from the funds held
+fn require_vault_credited(vault: &AccountInfo, balance_before: u64, amount: u64) -> Result<()> {
+    require!(
+        vault.lamports() == balance_before.checked_add(amount).unwrap(),
+        ErrorCode::DepositAmountMismatch
+    );
+    Ok(())
+}
+
+/// Start the match timer of a fully funded wager once both players are ready (always the
+/// case without `require_ready`)
+fn start_match_when_ready(wager: &mut Wager, current_time: i64) {
+    if wager.start_time != 0 {
+        return;
+    }
+    if wager.require_ready && !(wager.player1_ready && wager.player2_ready) {
+        msg!("Both players deposited! Waiting for both to ready up");
+        return;
+    }
+    wager.start_time = wager.match_start_time(current_time);
+    msg!("Both players deposited! Timer starts at {}: {} seconds", wager.start_time, TIMEOUT_SECONDS);
+}
+
+/// Under `strict_fee_recipient`, neither player may be the wager's fee recipient
+fn require_fee_recipient_not_player(wager: &Wager) -> Result<()> {
+    require!(
+        !wager.strict_fee_recipient || wager.player_number(&wager.fee_recipient).is_none(),
+        ErrorCode::FeeRecipientIsPlayer
+    );
+    Ok(())
+}
+
+/// Ensure the vault holds at least `expected_pool` on top of its own rent reserve
+fn require_vault_balance(vault: &AccountInfo, expected_pool: u64) -> Result<()> {
+    let rent_reserve = Rent::get()?.minimum_balance(0);
+    let required = expected_pool
+        .checked_add(rent_reserve)
+        .ok_or(ErrorCode::InsufficientVaultBalance)?;
+    require!(vault.lamports() >= required, ErrorCode::InsufficientVaultBalance);
+    Ok(())
+}
+
+/// Validate the options and write a newly created wager and its pair's stats, for
+/// `initialize_wager` and `initialize_open_wager`. `pda_seed` is the second seed the
+/// wager, vault and pair PDAs were derived from.
+#[allow(clippy::too_many_arguments)]
+fn init_wager<'info>(
+    wager: &mut Account<'info, Wager>,
+    vault: &AccountInfo<'info>,
+    pair_stats: &mut Account<'info, PairStats>,
+    payer: Pubkey,
+    bumps: &InitializeWagerBumps,
+    player1: Pubkey,
+    player2: Pubkey,
+    pda_seed: Pubkey,
+    arbiter: Pubkey,
+    fee_recipient: Pubkey,
+    wager_amount: u64,
+    options: WagerOptions,
+) -> Result<()> {
+    require!(player1 != player2, ErrorCode::SamePlayer);
+    require!(wager_amount > 0, ErrorCode::InvalidWagerAmount);
+    require!(
+        options.payer_dust_bps as u64 <= BPS_DENOMINATOR,
+        ErrorCode::InvalidDustSplit
+    );
+    require!(
+        options.fast_threshold_seconds >= 0 && options.fast_rebate_bps as u64 <= BPS_DENOMINATOR,
+        ErrorCode::InvalidFastRebate
+    );
+    
+    let clock = Clock::get()?;
+    let current_time = clock.unix_timestamp;
+    require!(
+        options.scheduled_start == 0 || options.scheduled_start > current_time,
+        ErrorCode::InvalidScheduledStart
+    );
+    require!(
+        options.start_delay_seconds >= 0 && options.start_delay_seconds <= MAX_START_DELAY_SECONDS,
+        ErrorCode::InvalidStartDelay
+    );
+    // Event-bound wagers: the nominal final deadline (both deposit windows plus the
+    // match timeout) must fall within the configured lifetime of the current epoch
+    require!(options.max_lifetime_seconds >= 0, ErrorCode::WagerLifetimeExceeded);
+    if options.max_lifetime_seconds > 0 {
+        let latest_deadline = current_time
+            .max(options.scheduled_start)
+            .checked_add(DEPOSIT_TIMEOUT_SECONDS * 2 + options.start_delay_seconds + TIMEOUT_SECONDS)
+            .unwrap();
+        let event_end = clock
+            .epoch_start_timestamp
+            .checked_add(options.max_lifetime_seconds)
+            .ok_or(ErrorCode::WagerLifetimeExceeded)?;
+        require!(latest_deadline <= event_end, ErrorCode::WagerLifetimeExceeded);
+    }
+    require!(options.claim_window_seconds >= 0, ErrorCode::InvalidClaimWindow);
+    // A minimum longer than the match timeout would leave no window to declare in
+    require!(
+        options.min_match_seconds >= 0 && options.min_match_seconds <= TIMEOUT_SECONDS,
+        ErrorCode::InvalidMinMatchDuration
+    );
+    require!(
+        options.arbiter_rotation.len() <= MAX_ARBITER_ROTATION,
+        ErrorCode::ArbiterRotationTooLong
+    );
+    require!(!is_reserved_address(&fee_recipient), ErrorCode::ReservedPayoutAddress);
+    if let Some(leg) = options.insurance {
+        require!(leg.insurance_bps as u64 <= BPS_DENOMINATOR, ErrorCode::InvalidInsuranceLeg);
+    }
+    if let Some(waiver) = options.fee_waiver {
+        require!(waiver.waiver_bps as u64 <= BPS_DENOMINATOR, ErrorCode::InvalidFeeWaiver);
+    }
+    if let Some(waiver) = options.new_player_waiver {
+        require!(waiver.waiver_bps as u64 <= BPS_DENOMINATOR, ErrorCode::InvalidFeeWaiver);
+    }
+    require!(
+        FEE_PERCENTAGE * 100 + options.arbiter_fee_bps as u64 <= MAX_COMBINED_FEE_BPS,
+        ErrorCode::CombinedFeeTooHigh
+    );
+    require!(
+        options.keeper_reward_bps <= MAX_KEEPER_REWARD_BPS,
+        ErrorCode::KeeperRewardTooHigh
+    );
+    // Accumulated fees: the fee recipient is the authority's fee vault, so every fee and
+    // dust sweep lands there for `withdraw_fees`
+    if let Some(authority) = options.fee_vault_authority {
+        let (fee_vault, _) = Pubkey::find_program_address(&[b"fee_vault", authority.as_ref()], &crate::ID);
+        require!(fee_recipient == fee_vault, ErrorCode::InvalidFeeVault);
+    }
+    if let Some(split) = options.fee_split {
+        require!(
+            split.primary_bps as u64 + split.secondary_bps as u64 == BPS_DENOMINATOR,
+            ErrorCode::InvalidFeeSplit
+        );
+        require!(
+            !is_reserved_address(&split.secondary_recipient),
+            ErrorCode::ReservedPayoutAddress
+        );
+    }
+    
+    // Series play: each round is arbitrated by the next arbiter in the rotation. The
+    // round index is the number of wagers this pair has already completed and closed.
+    let series_round = pair_stats.settlement_count;
+    let arbiter = if options.arbiter_rotation.is_empty() {
+        arbiter
+    } else {
+        let index = series_round % options.arbiter_rotation.len() as u64;
+        options.arbiter_rotation[index as usize]
+    };
+    
+    // Calculate total initialization cost (rent for wager + vault PDAs)
+    let rent = Rent::get()?;
+    let wager_rent = rent.minimum_balance(8 + Wager::INIT_SPACE);
+    let vault_rent = rent.minimum_balance(0); // Vault has no data
+    let total_initialization_cost = wager_rent.checked_add(vault_rent).unwrap();
+    if options.deduct_init_cost_from_pool {
+        require_stake_covers_init_cost(wager_amount, total_initialization_cost)?;
+    }
+    
+    wager.player1 = player1;
+    wager.player2 = player2;
+    wager.pda_seed = pda_seed;
+    wager.pair_seed = pda_seed;
+    wager.arbiter = arbiter;
+    wager.arbiter_changes = 0;
+    wager.house_wager = false;
+    wager.fee_recipient = fee_recipient;
+    wager.wager_amount = wager_amount;
+    wager.creation_time = current_time;
+    wager.start_time = 0;
+    wager.first_deposit_time = 0;
+    wager.winner = None;
+    // `init` derives these with `find_program_address`, so the stored bumps are canonical;
+    // every later instruction pins the PDAs to them through its `seeds`/`bump` constraints
+    wager.bump = bumps.wager;
+    wager.vault_bump = bumps.vault;
+    wager.initialization_cost = total_initialization_cost;
+    wager.payer = payer;
+    wager.payer_dust_bps = options.payer_dust_bps;
+    wager.deduct_init_cost_from_pool = options.deduct_init_cost_from_pool;
+    wager.scheduled_start = options.scheduled_start;
+    wager.pay_yield_to_winner = options.pay_yield_to_winner;
+    wager.yield_paid = 0;
+    wager.player1_payout = None;
+    wager.player2_payout = None;
+    wager.fast_threshold_seconds = options.fast_threshold_seconds;
+    wager.fast_rebate_bps = options.fast_rebate_bps;
+    wager.tournament_id = options.tournament_id;
+    wager.arbiter_rotation = options.arbiter_rotation;
+    wager.series_round = series_round;
+    wager.fee_split = options.fee_split;
+    wager.result_commitment = None;
+    wager.scores = None;
+    wager.cancelled_time = 0;
+    wager.claim_window_seconds = options.claim_window_seconds;
+    wager.declared_time = 0;
+    wager.unclaimed_winnings = 0;
+    wager.min_match_seconds = options.min_match_seconds;
+    wager.fee_waiver = options.fee_waiver;
+    wager.game_ref = options.game_ref;
+    wager.insurance = options.insurance;
+    wager.start_delay_seconds = options.start_delay_seconds;
+    wager.escrow_authority = options.escrow_authority;
+    wager.version = WAGER_VERSION;
+    wager.has_side_pool = false;
+    // A new wager starts out `Created`, announced by `WagerInitialized`
+    wager.status = WagerStatus::Created;
+    wager.new_player_waiver = options.new_player_waiver;
+    wager.arbiter_fee_bps = options.arbiter_fee_bps;
+    wager.round_fee_down = options.round_fee_down;
+    wager.strict_isolation = options.strict_isolation;
+    wager.close_on_settle = options.close_on_settle;
+    wager.keeper_reward_bps = options.keeper_reward_bps;
+    wager.strict_fee_recipient = options.strict_fee_recipient;
+    require_fee_recipient_not_player(wager)?;
+    wager.fee_vault_authority = options.fee_vault_authority;
+    wager.require_ready = options.require_ready;
+    wager.player1_ready = false;
+    wager.player2_ready = false;
+    wager.deposits_completed_time = 0;
+    wager.cap_active_games = options.cap_active_games;
+    wager.settlements_frozen = false;
+    wager.expiry_time = current_time
+        .max(options.scheduled_start)
+        .checked_add(WAGER_EXPIRY_SECONDS)
+        .unwrap();
+    // Anyone can send lamports to the vault address before it is created; `init` then
+    // only tops it up to rent. Record any excess so it is never mistaken for stake or
+    // yield - it is left in the vault and swept with the dust on close.
+    wager.vault_prefund = vault.lamports().saturating_sub(vault_rent);
+    
+    // Rate-limit re-initialization of the same pair to curb create/cancel griefing
+    let epoch = clock.epoch;
+    if pair_stats.initialization_count == 0 {
+        pair_stats.player1 = player1;
+        pair_stats.player2 = player2;
+        pair_stats.bump = bumps.pair_stats;
+    }
+    pair_stats.record_initialization(epoch, current_time)?;
+    
+    msg!("Wager initialized: {} lamports per player", wager_amount);
+    if options.deduct_init_cost_from_pool {
+        msg!("Initialization cost: {} lamports (will be deducted from final payout)", total_initialization_cost);
+    } else {
+        msg!("Initialization cost: {} lamports (paid by payer, refunded on close)", total_initialization_cost);
+    }
+    msg!("Player 1: {}", player1);
+    msg!("Player 2: {}", player2);
+    msg!("Arbiter: {}", arbiter);
+    msg!("Fee Recipient: {}", fee_recipient);
+    if options.scheduled_start != 0 {
+        msg!("Scheduled start: {}", options.scheduled_start);
+    }
+    if options.claim_window_seconds > 0 {
+        msg!("Winnings held for claiming: {} seconds", options.claim_window_seconds);
+    }
+    if wager.vault_prefund > 0 {
+        msg!("Vault was pre-funded with {} lamports (excluded from the pool)", wager.vault_prefund);
+    }
+    
+    emit!(WagerInitialized {
+        wager: wager.key(),
+        player1,
+        player2,
+        arbiter,
+        fee_recipient,
+        wager_amount,
+        tournament_id: options.tournament_id,
+        game_ref: options.game_ref,
+    });
+    
+    Ok(())
+}
+
+/// Second seed of an open challenge's PDAs, standing in for player 2's key
+pub fn open_wager_seed(nonce: u64) -> Pubkey {
+    let seed = anchor_lang::solana_program::hash::hashv(&[b"open_wager", &nonce.to_le_bytes()]);
+    Pubkey::new_from_array(seed.to_bytes())
+}
+
+/// Second seed of a rematch's PDAs, so each rematch of `prior_wager` gets accounts of its own
+pub fn rematch_wager_seed(prior_wager: &Pubkey, nonce: u64) -> Pubkey {
+    let seed = anchor_lang::solana_program::hash::hashv(&[b"rematch", prior_wager.as_ref(), &nonce.to_le_bytes()]);
+    Pubkey::new_from_array(seed.to_bytes())
+}
+
+/// Addresses that must never receive payouts: the system program, the sysvar
+/// program and its sysvar accounts, and this program itself
+fn is_reserved_address(key: &Pubkey) -> bool {
+    use anchor_lang::solana_program::sysvar;
+    const SYSVAR_IDS: [Pubkey; 13] = [
+        sysvar::ID,
+        sysvar::clock::ID,
+        sysvar::epoch_rewards::ID,
+        sysvar::epoch_schedule::ID,
+        sysvar::fees::ID,
+        sysvar::instructions::ID,
+        sysvar::last_restart_slot::ID,
+        sysvar::recent_blockhashes::ID,
+        sysvar::rent::ID,
+        sysvar::rewards::ID,
+        sysvar::slot_hashes::ID,
+        sysvar::slot_history::ID,
+        sysvar::stake_history::ID,
+    ];
+    *key == anchor_lang::system_program::ID || SYSVAR_IDS.contains(key) || *key == crate::ID
+}
+
+/// Call off a game both players deposited into without a winner (`arbiter_void`,
+/// `mutual_void`): no timeout has to pass, and the stakes are returned on the same terms
+/// as a timeout refund (init cost deducted only if configured). Returns the refund each.
+fn void_match<'info>(
+    wager: &mut Account<'info, Wager>,
+    vault: &AccountInfo<'info>,
+    player1: &AccountInfo<'info>,
+    player2: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+) -> Result<u64> {
+    require!(
+        wager.player1_deposited() && wager.player2_deposited(),
+        ErrorCode::BothPlayersNotDeposited
+    );
+    require_vault_balance(vault, wager.principal()?)?;
+    let refund_amount = wager.refund_amount_each()?;
+    
+    // Mark settled before moving funds; no winner is recorded
+    set_status(wager, WagerStatus::Refunded)?;
+    
+    pay_from_vault(wager, vault, player1, system_program, refund_amount)?;
+    pay_from_vault(wager, vault, player2, system_program, refund_amount)?;
+    
+    let [stake1, stake2] = wager.stakes();
+    emit!(WagerRefunded {
+        wager: wager.key(),
+        player1_net: net_change(refund_amount, stake1),
+        player2_net: net_change(refund_amount, stake2),
+        tournament_id: wager.tournament_id,
+    });
+    
+    Ok(refund_amount)
+}
+
+/// Shared arbiter settlement for `declare_winner` and `reveal_result`: validates the
+/// arbiter and the match window, then pays out
+fn settle_winner(accounts: &mut DeclareWinner, winner: u8, current_time: i64) -> Result<SettlementResult> {
+    require_arbiter_can_declare(&accounts.wager, accounts.arbiter.key(), winner, current_time, true)?;
+    require_escrow_signature(&accounts.wager, accounts.escrow_authority.as_ref())?;
+    if accounts.wager.strict_isolation {
+        let instructions = accounts.instructions.as_ref().ok_or(ErrorCode::MissingInstructionsSysvar)?;
+        let guarded = [accounts.wager.key(), accounts.vault.key(), accounts.winner_account.key()];
+        require_isolated(instructions, &guarded)?;
+    }
+    let fee_waiver_bps = arbiter_fee_waiver_bps(&accounts.wager, accounts.arbiter_stats.as_ref())?
+        .max(new_player_fee_waiver_bps(&accounts.wager, accounts.player1_stats.as_ref(), accounts.player2_stats.as_ref())?);
+    
+    let wager = &mut accounts.wager;
+    let result = pay_out_winner(
+        wager,
+        &accounts.vault,
+        &accounts.winner_account,
+        &accounts.fee_recipient,
+        accounts.secondary_fee_recipient.as_deref(),
+        accounts.insurance_pool.as_mut(),
+        Some(accounts.arbiter.as_ref()),
+        &accounts.system_program,
+        winner,
+        current_time,
+        fee_waiver_bps,
+    )?;
+    
+    if let Some(arbiter_stats) = accounts.arbiter_stats.as_mut() {
+        arbiter_stats.settled_count = arbiter_stats.settled_count.checked_add(1).unwrap();
+    }
+    record_games_played(accounts.player1_stats.as_mut(), accounts.player2_stats.as_mut());
+    record_receipt(
+        accounts.receipt.as_mut(),
+        &accounts.wager,
+        (result.winner_amount, result.fee_amount),
+        [0, 0],
+        accounts.arbiter.key(),
+        current_time,
+    );
+    
+    close_after_settlement(
+        &mut accounts.wager,
+        &accounts.vault,
+        accounts.payer.as_ref(),
+        Some(&accounts.fee_recipient),
+        accounts.pair_stats.as_mut(),
+        &accounts.system_program,
+    )?;
+    
+    Ok(result)
+}
+
+/// Settle a drawn `declare_result`: both stakes are returned on the same terms as
+/// `arbiter_void`, with no winner recorded and no fee taken
+fn settle_draw(accounts: &mut DeclareWinner, current_time: i64) -> Result<()> {
+    require_arbiter_can_decide(&accounts.wager, accounts.arbiter.key(), current_time, true)?;
+    require_escrow_signature(&accounts.wager, accounts.escrow_authority.as_ref())?;
+    let (Some(player1), Some(player2)) = (accounts.player1.as_ref(), accounts.player2.as_ref()) else {
+        return err!(ErrorCode::DrawAccountsMissing);
+    };
+    require_vault_balance(&accounts.vault, accounts.wager.principal()?)?;
+    let refund_amount = accounts.wager.refund_amount_each()?;
+    
+    // Mark settled before moving funds; no winner is recorded
+    let wager = &mut accounts.wager;
+    set_status(wager, WagerStatus::Refunded)?;
+    
+    pay_from_vault(&accounts.wager, &accounts.vault, player1, &accounts.system_program, refund_amount)?;
+    pay_from_vault(&accounts.wager, &accounts.vault, player2, &accounts.system_program, refund_amount)?;
+    
+    msg!("Match drawn, both players refunded: {} lamports each", refund_amount);
+    
+    if let Some(arbiter_stats) = accounts.arbiter_stats.as_mut() {
+        arbiter_stats.settled_count = arbiter_stats.settled_count.checked_add(1).unwrap();
+    }
+    record_games_played(accounts.player1_stats.as_mut(), accounts.player2_stats.as_mut());
+    record_receipt(
+        accounts.receipt.as_mut(),
+        &accounts.wager,
+        (0, 0),
+        [refund_amount, refund_amount],
+        accounts.arbiter.key(),
+        current_time,
+    );
+    
+    let [stake1, stake2] = accounts.wager.stakes();
+    emit!(WagerRefunded {
+        wager: accounts.wager.key(),
+        player1_net: net_change(refund_amount, stake1),
+        player2_net: net_change(refund_amount, stake2),
+        tournament_id: accounts.wager.tournament_id,
+    });
+    
+    close_after_settlement(
+        &mut accounts.wager,
+        &accounts.vault,
+        accounts.payer.as_ref(),
+        Some(&accounts.fee_recipient),
+        accounts.pair_stats.as_mut(),
+        &accounts.system_program,
+    )
+}
+
+/// Refund both players of a fully funded match whose timeout has expired, each getting
+/// half of the pool after the initialization cost (if configured)
+fn refund_expired_match<'info>(
+    wager: &mut Account<'info, Wager>,
+    vault: &AccountInfo<'info>,
+    player1: &AccountInfo<'info>,
+    player2: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+    keeper_reward_each: u64,
+    current_time: i64,
+) -> Result<()> {
+    require!(!wager.is_settled(), ErrorCode::WagerAlreadySettled);
+    require!(
+        wager.player1_deposited() && wager.player2_deposited(),
+        ErrorCode::BothPlayersNotDeposited
+    );
+    // Without a start time, `current_time - 0` would trivially exceed the timeout
+    require!(wager.start_time != 0, ErrorCode::MatchNotStarted);
+    
+    // A clock reading behind start_time only increases the remaining time, so skew
+    // can delay a refund but never enable one early
+    require!(wager.match_timed_out(current_time), ErrorCode::TimeoutNotExpired);
+    
+    require_vault_balance(vault, wager.principal()?)?;
+    
+    // Deduct initialization cost from total pool before refunding; a keeper's reward
+    // stays in the vault for the caller to pay out
+    let refund_amount = wager.refund_amount_each()?.checked_sub(keeper_reward_each).unwrap();
+    
+    // Mark settled before moving funds
+    set_status(wager, WagerStatus::Refunded)?;
+    
+    // Refund player 1 from vault
+    pay_from_vault(wager, vault, player1, system_program, refund_amount)?;
+    
+    // Refund player 2 from vault
+    pay_from_vault(wager, vault, player2, system_program, refund_amount)?;
+    
+    msg!("Refund issued to both players: {} lamports each", refund_amount);
+    
+    let [stake1, stake2] = wager.stakes();
+    emit!(WagerRefunded {
+        wager: wager.key(),
+        player1_net: net_change(refund_amount, stake1),
+        player2_net: net_change(refund_amount, stake2),
+        tournament_id: wager.tournament_id,
+    });
+    
+    Ok(())
+}
+
+/// Cancel a wager that is not fully funded and refund whoever deposited (less the
+/// initialization cost, if configured). Outside a mutual cancel the deposit timeout must
+/// have passed.
+fn cancel_and_refund(accounts: &mut CancelWager, enforce_deposit_timeout: bool) -> Result<()> {
+    let wager = &accounts.wager;
+    
+    require!(!wager.is_settled(), ErrorCode::WagerAlreadySettled);
+    // Terminal instructions exclude each other explicitly: a declared winner is never
+    // followed by a refund, whatever the settled flag and deposit state say
+    require!(wager.winner.is_none(), ErrorCode::WagerAlreadySettled);
+    require!(
+        !(wager.player1_deposited() && wager.player2_deposited()),
+        ErrorCode::BothPlayersAlreadyDeposited
+    );
+    require!(wager.wager_amount > wager.pool_init_cost(), ErrorCode::WagerTooSmallForRent);
+    
+    let current_time = Clock::get()?.unix_timestamp;
+    if enforce_deposit_timeout {
+        require!(wager.deposit_window_expired(current_time), ErrorCode::DepositTimeoutNotExpired);
+    }
+    
+    let player1_deposited = wager.player1_deposited();
+    let player2_deposited = wager.player2_deposited();
+    
+    // Deduct initialization cost from the depositor's stake since the opponent didn't
+    // show up (if configured)
+    let [stake1, stake2] = wager.stakes();
+    let pool_init_cost = wager.pool_init_cost();
+    let stake = if player2_deposited { stake2 } else { stake1 };
+    let refund_amount = stake.checked_sub(pool_init_cost).ok_or(ErrorCode::WagerTooSmallForRent)?;
+    
+    // Mark settled before moving funds
+    let wager = &mut accounts.wager;
+    
+    wager.cancelled_time = current_time;
+    let status = wager.status.cancelled();
+    set_status(wager, status)?;
+    
+    // Refund from the vault
+    if player1_deposited {
+        pay_from_vault(
+            &accounts.wager,
+            &accounts.vault,
+            &accounts.player1,
+            &accounts.system_program,
+            refund_amount,
+        )?;
+        msg!("Player 1 refunded: {} lamports (after deducting {} lamports initialization cost)", 
+            refund_amount,
+            pool_init_cost);
+    }
+    
+    if player2_deposited {
+        let player2 = accounts.player2.as_ref().ok_or(ErrorCode::UnauthorizedPlayer)?;
+        pay_from_vault(
+            &accounts.wager,
+            &accounts.vault,
+            player2,
+            &accounts.system_program,
+            refund_amount,
+        )?;
+        msg!("Player 2 refunded: {} lamports (after deducting {} lamports initialization cost)", 
+            refund_amount,
+            pool_init_cost);
+    }
+    
+    if enforce_deposit_timeout {
+        msg!("Wager cancelled due to incomplete deposits after {} seconds", DEPOSIT_TIMEOUT_SECONDS);
+    } else {
+        msg!("Wager cancelled by both players");
+    }
+    
+    record_receipt(
+        accounts.receipt.as_mut(),
+        &accounts.wager,
+        (0, 0),
+        [
+            if player1_deposited { refund_amount } else { 0 },
+            if player2_deposited { refund_amount } else { 0 },
+        ],
+        receipt_signer(accounts.receipt_payer.as_ref()),
+        current_time,
+    );
+    
+    // A player who never deposited neither paid nor received anything
+    emit!(WagerRefunded {
+        wager: accounts.wager.key(),
+        player1_net: if player1_deposited { net_change(refund_amount, stake1) } else { 0 },
+        player2_net: if player2_deposited { net_change(refund_amount, stake2) } else { 0 },
+        tournament_id: accounts.wager.tournament_id,
+    });
+    
+    close_after_settlement(
+        &mut accounts.wager,
+        &accounts.vault,
+        accounts.payer.as_ref(),
+        accounts.fee_recipient.as_ref(),
+        accounts.pair_stats.as_mut(),
+        &accounts.system_program,
+    )
+}
+
+/// Checks that `arbiter` may declare `winner` on this wager at `current_time`. The match
+/// timeout is only waived when both players have consented to the result.
+fn require_arbiter_can_declare(
+    wager: &Wager,
+    arbiter: Pubkey,
+    winner: u8,
+    current_time: i64,
+    enforce_timeout: bool,
+) -> Result<()> {
+    require!(winner == 1 || winner == 2, ErrorCode::InvalidWinner);
+    require_arbiter_can_decide(wager, arbiter, current_time, enforce_timeout)
+}
+
+/// Checks that `arbiter` may decide the match at `current_time`, whether by naming a
+/// winner or by declaring a draw
+fn require_arbiter_can_decide(
+    wager: &Wager,
+    arbiter: Pubkey,
+    current_time: i64,
+    enforce_timeout: bool,
+) -> Result<()> {
+    require!(!wager.is_settled(), ErrorCode::WagerAlreadySettled);
+    require!(arbiter == wager.arbiter, ErrorCode::UnauthorizedArbiter);
+    require!(
+        wager.player1_deposited() && wager.player2_deposited(),
+        ErrorCode::BothPlayersNotDeposited
+    );
+    // Both deposits do not always start the match: a `require_ready` wager waits for the
+    // ready handshake. The zero sentinel must never reach the timeout math below.
+    require!(wager.start_time != 0, ErrorCode::MatchNotStarted);
+    
+    // A scheduled match cannot be decided before its start time, and a clock reading
+    // behind start_time must not slip under the timeout check as a negative difference
+    let elapsed = match_elapsed(wager.start_time, current_time, CLOCK_SKEW_TOLERANCE_SECONDS)
+        .ok_or(ErrorCode::MatchNotStarted)?;
+    // Instant declarations are rejected so colluding accounts cannot wash-trade
+    require!(elapsed >= wager.min_match_seconds, ErrorCode::MatchTooShort);
+    require!(!enforce_timeout || elapsed <= TIMEOUT_SECONDS, ErrorCode::TimeoutExpired);
+    Ok(())
+}
+
+/// Move the wager to `status` and log the change for indexers. Rejects moves outside the
+/// lifecycle. The stored deposit and settlement flags are only written here (and at
+/// initialization), as copies of what the status implies, so they never disagree with it.
+fn set_status(wager: &mut Account<Wager>, status: WagerStatus) -> Result<()> {
+    require!(wager.status.can_transition_to(status), ErrorCode::InvalidStatusTransition);
+    wager.status = status;
+    emit!(WagerStatusChanged {
+        wager: wager.key(),
+        status,
+    });
+    Ok(())
+}
+
+/// Result message the arbiter signs for `declare_winner_signed`
+pub fn signed_result_message(wager: &Pubkey, winner: u8, nonce: u64) -> [u8; SIGNED_RESULT_LEN] {
+    let mut message = [0u8; SIGNED_RESULT_LEN];
+    message[..32].copy_from_slice(wager.as_ref());
+    message[32] = winner;
+    message[33..].copy_from_slice(&nonce.to_le_bytes());
+    message
+}
+
+/// Public key and message of an ed25519 program instruction carrying exactly one
+/// signature, with all of its data inline. Offsets pointing into other instructions are
+/// rejected, since they could make the verified bytes differ from the ones read here.
+pub fn parse_ed25519_instruction(data: &[u8]) -> Option<(Pubkey, &[u8])> {
+    // Header: signature count, padding, then one 14-byte offsets entry
+    if data.len() < 16 || data[0] != 1 {
+        return None;
+    }
+    let field = |i: usize| u16::from_le_bytes([data[2 + 2 * i], data[3 + 2 * i]]);
+    let (signature_ix, public_key_offset, public_key_ix) = (field(1), field(2) as usize, field(3));
+    let (message_offset, message_size, message_ix) = (field(4) as usize, field(5) as usize, field(6));
+    if signature_ix != u16::MAX || public_key_ix != u16::MAX || message_ix != u16::MAX {
+        return None;
+    }
+    let public_key = data.get(public_key_offset..public_key_offset.checked_add(32)?)?;
+    let message = data.get(message_offset..message_offset.checked_add(message_size)?)?;
+    Some((Pubkey::try_from(public_key).ok()?, message))
+}
+
+/// Requires the instruction right before the current one to be an ed25519 program
+/// instruction that verified `signer`'s signature over exactly `message`
+fn require_ed25519_signature(instructions: &AccountInfo, signer: &Pubkey, message: &[u8]) -> Result<()> {
+    let current_index = load_current_index_checked(instructions)?;
+    require!(current_index > 0, ErrorCode::InvalidSignedResult);
+    let verify_ix = load_instruction_at_checked(current_index as usize - 1, instructions)?;
+    require!(verify_ix.program_id == ed25519_program::ID, ErrorCode::InvalidSignedResult);
+    
+    let (public_key, signed) = parse_ed25519_instruction(&verify_ix.data).ok_or(ErrorCode::InvalidSignedResult)?;
+    require!(public_key == *signer && signed == message, ErrorCode::InvalidSignedResult);
+    Ok(())
+}
+
+/// Fill in the settlement receipt, if one was passed, with the round's final amounts:
+/// `payout` is the winner's amount and the fee, `refunds` what each player got back
+fn record_receipt(
+    receipt: Option<&mut Account<SettlementReceipt>>,
+    wager: &Account<Wager>,
+    payout: (u64, u64),
+    refunds: [u64; 2],
+    settled_by: Pubkey,
+    settled_at: i64,
+) {
+    let Some(receipt) = receipt else {
+        return;
+    };
+    receipt.wager = wager.key();
+    receipt.series_round = wager.series_round;
+    receipt.status = wager.status;
+    receipt.winner = wager.winner;
+    receipt.winner_amount = payout.0;
+    receipt.fee_amount = payout.1;
+    receipt.refunds = refunds;
+    receipt.settled_by = settled_by;
+    receipt.settled_at = settled_at;
+}
+
+/// Whoever signed for the receipt of a settlement that has no signer of its own
+fn receipt_signer(receipt_payer: Option<&Signer>) -> Pubkey {
+    receipt_payer.map(|signer| signer.key()).unwrap_or_default()
+}
+
+/// Requires every other instruction in the transaction to leave the `guarded` accounts
+/// alone, so a settlement cannot be bundled with a transfer touching the same accounts
+fn require_isolated(instructions: &AccountInfo, guarded: &[Pubkey]) -> Result<()> {
+    let current_index = load_current_index_checked(instructions)? as usize;
+    let mut index = 0;
+    while let Ok(instruction) = load_instruction_at_checked(index, instructions) {
+        if index != current_index {
+            require!(
+                !instruction.accounts.iter().any(|meta| guarded.contains(&meta.pubkey)),
+                ErrorCode::InstructionNotIsolated
+            );
+        }
+        index += 1;
+    }
+    Ok(())
+}
+
+/// Compliance hold: a wager that names an escrow authority only pays out a winner
+/// when that authority co-signs the settlement
+fn require_escrow_signature(wager: &Wager, escrow_authority: Option<&Signer>) -> Result<()> {
+    if let Some(expected) = wager.escrow_authority {
+        require!(
+            escrow_authority.is_some_and(|signer| signer.key() == expected),
+            ErrorCode::MissingEscrowSignature
+        );
+    }
+    Ok(())
+}
+
+/// Onboarding incentive: part of the fee is waived for the arbiter's first matches
+fn arbiter_fee_waiver_bps(wager: &Wager, arbiter_stats: Option<&Account<ArbiterStats>>) -> Result<u64> {
+    let Some(waiver) = wager.fee_waiver else {
+        return Ok(0);
+    };
+    let arbiter_stats = arbiter_stats.ok_or(ErrorCode::InvalidArbiterStats)?;
+    if arbiter_stats.settled_count < waiver.matches {
+        Ok(waiver.waiver_bps as u64)
+    } else {
+        Ok(0)
+    }
+}
+
+/// Share of the fee waived because either player is still within the wager's new-player
+/// waiver; both players' stats accounts are required when the wager has one
+fn new_player_fee_waiver_bps(
+    wager: &Wager,
+    player1_stats: Option<&Account<PlayerStats>>,
+    player2_stats: Option<&Account<PlayerStats>>,
+) -> Result<u64> {
+    let Some(waiver) = wager.new_player_waiver else {
+        return Ok(0);
+    };
+    let player1_stats = player1_stats.ok_or(ErrorCode::InvalidPlayerStats)?;
+    let player2_stats = player2_stats.ok_or(ErrorCode::InvalidPlayerStats)?;
+    if player1_stats.games_played < waiver.matches || player2_stats.games_played < waiver.matches {
+        Ok(waiver.waiver_bps as u64)
+    } else {
+        Ok(0)
+    }
+}
+
+/// Count the settled game for each player whose stats account was passed
+fn record_games_played<'info>(
+    player1_stats: Option<&mut Account<'info, PlayerStats>>,
+    player2_stats: Option<&mut Account<'info, PlayerStats>>,
+) {
+    for player_stats in [player1_stats, player2_stats].into_iter().flatten() {
+        player_stats.games_played = player_stats.games_played.checked_add(1).unwrap();
+    }
+}
+
+/// Pays the winner (plus any fast-match rebate, fee waiver and yield) and the fee, and
+/// marks the wager settled. Callers validate who may settle and when.
+#[allow(clippy::too_many_arguments)]
+fn pay_out_winner<'info>(
+    wager: &mut Account<'info, Wager>,
+    vault: &AccountInfo<'info>,
+    winner_account: &AccountInfo<'info>,
+    fee_recipient: &AccountInfo<'info>,
+    secondary_fee_recipient: Option<&AccountInfo<'info>>,
+    insurance_pool: Option<&mut Account<'info, InsurancePool>>,
+    arbiter: Option<&AccountInfo<'info>>,
+    system_program: &Program<'info, System>,
+    winner: u8,
+    current_time: i64,
+    fee_waiver_bps: u64,
+) -> Result<SettlementResult> {
+    require!(!wager.settlements_frozen, ErrorCode::SettlementsFrozen);
+    
+    // The vault must actually hold the pool; out-of-band funding or a short deposit
+    // must surface as a clean error rather than an underflow mid-payout
+    let principal = wager.principal()?;
+    require_vault_balance(vault, principal)?;
+    require_positive_winner_payout(wager, principal)?;
+    
+    let (winner_amount, fee_amount) = wager.split_pool(principal);
+    
+    // The arbiter's fee comes out of the winner's share when the arbiter declared the result
+    let arbiter_fee = if arbiter.is_some() {
+        wager.arbiter_fee(principal)
+    } else {
+        0
+    };
+    let winner_amount = winner_amount.checked_sub(arbiter_fee).unwrap();
+    
+    // Fast matches earn the winner a rebate of part of the fee
+    let fee_rebate = if wager.fast_threshold_seconds > 0
+        && match_elapsed(wager.start_time, current_time, CLOCK_SKEW_TOLERANCE_SECONDS)
+            .is_some_and(|elapsed| elapsed <= wager.fast_threshold_seconds)
+    {
+        fee_amount.checked_mul(wager.fast_rebate_bps as u64).unwrap().checked_div(BPS_DENOMINATOR).unwrap()
+    } else {
+        0
+    };
+    let winner_amount = winner_amount.checked_add(fee_rebate).unwrap();
+    let fee_amount = fee_amount.checked_sub(fee_rebate).unwrap();
+    
+    // A waived fee stays with the winner rather than the fee recipient
+    let fee_waived = fee_amount.checked_mul(fee_waiver_bps).unwrap().checked_div(BPS_DENOMINATOR).unwrap();
+    let winner_amount = winner_amount.checked_add(fee_waived).unwrap();
+    let fee_amount = fee_amount.checked_sub(fee_waived).unwrap();
+    
+    // Anything the vault holds above the staked principal and its rent reserve is
+    // accrued yield; it is paid to the winner on top of their share if configured
+    let accrued_yield = if wager.pay_yield_to_winner {
+        let vault_rent = Rent::get()?.minimum_balance(0);
+        vault.lamports()
+            .saturating_sub(principal)
+            .saturating_sub(vault_rent)
+            .saturating_sub(wager.vault_prefund)
+    } else {
+        0
+    };
+    let winner_amount = winner_amount.checked_add(accrued_yield).unwrap();
+    
+    // Winnings go to the winner's registered payout address, or their own key by default
+    require!(
+        winner_account.key() == wager.payout_address(winner),
+        ErrorCode::InvalidWinnerAccount
+    );
+    require!(
+        !is_reserved_address(winner_account.key)
+            && !is_reserved_address(fee_recipient.key),
+        ErrorCode::ReservedPayoutAddress
+    );
+    if wager.claim_window_seconds == 0 {
+        require_viable_payout(winner_account, winner_amount)?;
+    }
+    
+    // Mark settled before any value leaves the vault so a repeated declaration
+    // (even one bundled into the same transaction) fails the settled guard
+    wager.winner = Some(winner);
+    set_status(wager, WagerStatus::Settled)?;
+    wager.yield_paid = accrued_yield;
+    wager.declared_time = current_time;
+    
+    // Transfer winner amount, or hold it in the vault for the winner to claim
+    if wager.claim_window_seconds > 0 {
+        wager.unclaimed_winnings = winner_amount;
+    } else {
+        pay_from_vault(wager, vault, winner_account, system_program, winner_amount)?;
+    }
+    if let Some(arbiter) = arbiter.filter(|_| arbiter_fee > 0) {
+        pay_from_vault(wager, vault, arbiter, system_program, arbiter_fee)?;
+    }
+    
+    // Transfer fee amount (less any insurance leg, split with the secondary recipient if configured)
+    pay_fee(
+        wager,
+        vault,
+        fee_recipient,
+        secondary_fee_recipient,
+        insurance_pool,
+        system_program,
+        fee_amount,
+    )?;
+    
+    msg!("Winner declared: Player {}", winner);
+    msg!("Winner receives: {} lamports", winner_amount);
+    if fee_rebate > 0 {
+        msg!("Includes fast match fee rebate: {} lamports", fee_rebate);
+    }
+    if fee_waived > 0 {
+        msg!("Includes arbiter onboarding fee waiver: {} lamports", fee_waived);
+    }
+    if accrued_yield > 0 {
+        msg!("Includes accrued yield: {} lamports", accrued_yield);
+    }
+    msg!("Fee: {} lamports", fee_amount);
+    if arbiter_fee > 0 {
+        msg!("Arbiter fee: {} lamports", arbiter_fee);
+    }
+    if wager.unclaimed_winnings > 0 {
+        msg!("Winnings held for claiming until {}", current_time.saturating_add(wager.claim_window_seconds));
+    }
+    
+    let (player1_net, player2_net) = wager.net_changes(winner, winner_amount);
+    emit!(WinnerDeclared {
+        wager: wager.key(),
+        winner,
+        winner_amount,
+        fee_amount,
+        tournament_id: wager.tournament_id,
+        player1_net,
+        player2_net,
+    });
+    
+    Ok(SettlementResult {
+        winner,
+        winner_amount,
+        fee_amount,
+    })
+}
+
+/// Pay the fee from the vault. The insurance leg (if configured) is routed to the
+/// insurance pool first; the rest is split with the secondary recipient if the wager
+/// has a fee split. Any rounding remainder goes to the primary recipient.
+fn pay_fee<'info>(
+    wager: &Wager,
+    vault: &AccountInfo<'info>,
+    fee_recipient: &AccountInfo<'info>,
+    secondary_fee_recipient: Option<&AccountInfo<'info>>,
+    insurance_pool: Option<&mut Account<'info, InsurancePool>>,
+    system_program: &Program<'info, System>,
+    fee_amount: u64,
+) -> Result<()> {
+    let fee_amount = match wager.insurance {
+        Some(leg) => {
+            let pool = insurance_pool.ok_or(ErrorCode::InvalidInsurancePool)?;
+            require!(pool.key() == leg.pool, ErrorCode::InvalidInsurancePool);
+            
+            let insurance_amount = fee_amount.checked_mul(leg.insurance_bps as u64).unwrap().checked_div(BPS_DENOMINATOR).unwrap();
+            pay_from_vault(wager, vault, &pool.to_account_info(), system_program, insurance_amount)?;
+            pool.total_accrued = pool.total_accrued.checked_add(insurance_amount).unwrap();
+            
+            msg!("Insurance leg: {} lamports", insurance_amount);
+            fee_amount.checked_sub(insurance_amount).unwrap()
+        }
+        None => fee_amount,
+    };
+    
+    let Some(split) = wager.fee_split else {
+        return pay_from_vault(wager, vault, fee_recipient, system_program, fee_amount);
+    };
+    let secondary = secondary_fee_recipient.ok_or(ErrorCode::InvalidSecondaryFeeRecipient)?;
+    require!(
+        secondary.key() == split.secondary_recipient,
+        ErrorCode::InvalidSecondaryFeeRecipient
+    );
+    
+    let secondary_amount = fee_amount.checked_mul(split.secondary_bps as u64).unwrap().checked_div(BPS_DENOMINATOR).unwrap();
+    let primary_amount = fee_amount.checked_sub(secondary_amount).unwrap();
+    pay_from_vault(wager, vault, fee_recipient, system_program, primary_amount)?;
+    pay_from_vault(wager, vault, secondary, system_program, secondary_amount)?;
+    
+    msg!("Fee split: {} lamports primary, {} lamports secondary",
+        primary_amount,
+        secondary_amount);
+    Ok(())
+}
+
+/// Count a deposit into `wager` against the player's open-wager cap, if they track one.
+/// A `cap_active_games` wager requires the account, so the cap cannot be dodged by
+/// leaving it out. Depositing again into a wager already counted (e.g. after
+/// `reopen_wager`) takes no new slot.
+fn record_active_game(player_activity: Option<&mut Account<PlayerActivity>>, wager: &Account<Wager>) -> Result<()> {
+    let Some(player_activity) = player_activity else {
+        require!(!wager.cap_active_games, ErrorCode::PlayerActivityRequired);
+        return Ok(());
+    };
+    let wager = wager.key();
+    if player_activity.active_wagers.contains(&wager) {
+        return Ok(());
+    }
+    require!(
+        player_activity.active_wagers.len() < player_activity.max_active_games as usize,
+        ErrorCode::TooManyActiveGames
+    );
+    player_activity.active_wagers.push(wager);
+    Ok(())
+}
+
+/// Sweep a settled wager's vault down to exactly zero so it is garbage collected at the
+/// end of the transaction. The vault's own rent reserve always goes back to the payer;
+/// the dust above it is split as in `sweep_dust`.
+fn sweep_vault<'info>(
+    wager: &Wager,
+    vault: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    fee_recipient: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+) -> Result<()> {
+    sweep_dust(wager, vault, payer, fee_recipient, system_program)?;
+    
+    let vault_rent = vault.lamports();
+    pay_from_vault(wager, vault, payer, system_program, vault_rent)?;
+    require!(vault.lamports() == 0, ErrorCode::VaultNotEmpty);
+    
+    msg!("Payer recovered: {} lamports vault rent", vault_rent);
+    Ok(())
+}
+
+/// Pay out everything a settled wager's vault holds above its rent reserve (retained
+/// initialization cost, rounding remainders, pre-funding) as dust, split between the
+/// payer and the fee recipient per `payer_dust_bps`
+fn sweep_dust<'info>(
+    wager: &Wager,
+    vault: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    fee_recipient: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+) -> Result<()> {
+    // Held winnings would otherwise be swept up as dust
+    require!(wager.unclaimed_winnings == 0, ErrorCode::WinningsUnclaimed);
+    // Backers need the recorded outcome before the wager disappears
+    require!(!wager.has_side_pool, ErrorCode::SidePoolUnsettled);
+    
+    let vault_rent = Rent::get()?.minimum_balance(0);
+    let dust = vault.lamports().saturating_sub(vault_rent);
+    let payer_dust = dust.checked_mul(wager.payer_dust_bps as u64).unwrap().checked_div(BPS_DENOMINATOR).unwrap();
+    let fee_dust = dust.checked_sub(payer_dust).unwrap();
+    
+    pay_from_vault(wager, vault, fee_recipient, system_program, fee_dust)?;
+    pay_from_vault(wager, vault, payer, system_program, payer_dust)?;
+    
+    msg!("Payer recovered: {} lamports dust", payer_dust);
+    msg!("Fee recipient recovered: {} lamports dust", fee_dust);
+    Ok(())
+}
+
+/// With `close_on_settle`, finish a settlement as `close_wager` would: sweep the vault
+/// and close the wager to the payer in the same instruction. Skipped while the wager is
+/// still needed (winnings held for claiming, an open side pool), leaving it to
+/// `close_wager`.
+fn close_after_settlement<'info>(
+    wager: &mut Account<'info, Wager>,
+    vault: &AccountInfo<'info>,
+    payer: Option<&AccountInfo<'info>>,
+    fee_recipient: Option<&AccountInfo<'info>>,
+    pair_stats: Option<&mut Account<'info, PairStats>>,
+    system_program: &Program<'info, System>,
+) -> Result<()> {
+    if !wager.close_on_settle || wager.unclaimed_winnings > 0 || wager.has_side_pool {
+        return Ok(());
+    }
+    let (Some(payer), Some(fee_recipient), Some(pair_stats)) = (payer, fee_recipient, pair_stats) else {
+        return err!(ErrorCode::CloseAccountsMissing);
+    };
+    
+    sweep_vault(wager, vault, payer, fee_recipient, system_program)?;
+    pair_stats.settlement_count = pair_stats.settlement_count.checked_add(1).unwrap();
+    
+    // A `close` constraint cannot depend on the wager's flag, so the account is closed
+    // by hand; once it belongs to the system program Anchor skips writing it back on exit
+    wager.close(payer.clone())?;
+    msg!("Wager closed on settlement");
+    
+    Ok(())
+}
+
+/// Move lamports out of the system-owned vault with a transfer CPI signed by the vault PDA
+fn pay_from_vault<'info>(
+    wager: &Wager,
+    vault: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+    amount: u64,
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+    // An underfunded vault fails with a named error rather than deep in the transfer
+    require!(vault.lamports() >= amount, ErrorCode::VaultUnderflow);
+    let vault_bump = [wager.vault_bump];
+    let vault_seeds: &[&[u8]] = &[b"vault", wager.player1.as_ref(), wager.pda_seed.as_ref(), &vault_bump];
+    let signer_seeds = &[vault_seeds];
+    let cpi_context = CpiContext::new_with_signer(
+        system_program.to_account_info(),
+        Transfer {
+            from: vault.clone(),
+            to: to.clone(),
+        },
+        signer_seeds,
+    );
+    transfer(cpi_context, amount)
+}
+
+/// Move lamports out of a system-owned side vault, signed by its PDA
+fn pay_from_side_vault<'info>(
+    side_pool: &SidePool,
+    side: u8,
+    side_vault: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+    amount: u64,
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+    let vault_bump = [side_pool.vault_bumps[(side - 1) as usize]];
+    let side_seed = [side];
+    let vault_seeds: &[&[u8]] = &[b"side_vault", side_pool.wager.as_ref(), &side_seed, &vault_bump];
+    let signer_seeds = &[vault_seeds];
+    let cpi_context = CpiContext::new_with_signer(
+        system_program.to_account_info(),
+        Transfer {
+            from: side_vault.clone(),
+            to: to.clone(),
+        },
+        signer_seeds,
+    );
+    transfer(cpi_context, amount)
+}
+
+/// Lifetime activity for a player pair, shared by every wager between them
+#[account]
+#[derive(InitSpace)]
+pub struct PairStats {
+    pub player1: Pubkey,
+    pub player2: Pubkey,
+    pub initialization_count: u64,
+    /// Settled wagers that have been closed for this pair
+    pub settlement_count: u64,
+    pub epoch: u64,
+    pub epoch_initializations: u16,
+    /// When the pair's current wager was initialized (or rematched)
+    pub last_init_time: i64,
+    /// Minimum seconds between initializations, set by both players (0 = none)
+    pub init_cooldown_seconds: i64,
+    pub bump: u8,
+}
+
+impl PairStats {
+    /// Count a new wager for the pair, capped per epoch and spaced by the pair's cooldown
+    /// to curb create/cancel griefing and squatting on the pair's PDAs
+    pub fn record_initialization(&mut self, epoch: u64, now: i64) -> Result<()> {
+        require!(
+            now >= self.last_init_time.saturating_add(self.init_cooldown_seconds),
+            ErrorCode::InitCooldown
+        );
+        if self.epoch != epoch {
+            self.epoch = epoch;
+            self.epoch_initializations = 0;
+        }
+        require!(
+            self.epoch_initializations < MAX_PAIR_INITIALIZATIONS_PER_EPOCH,
+            ErrorCode::PairInitializationCapReached
+        );
+        self.epoch_initializations += 1;
+        self.initialization_count = self.initialization_count.checked_add(1).unwrap();
+        self.last_init_time = now;
+        Ok(())
+    }
+}
+
+/// Settled-match counter for an arbiter, across every wager they arbitrate
+#[account]
+#[derive(InitSpace)]
+pub struct ArbiterStats {
+    pub arbiter: Pubkey,
+    /// Winner declarations made with this account passed in
+    pub settled_count: u64,
+    pub bump: u8,
+}
+
+/// Settled-game counter for a player, across every wager they play
+#[account]
+#[derive(InitSpace)]
+pub struct PlayerStats {
+    pub player: Pubkey,
+    /// Arbiter-declared games settled with this account passed in
+    pub games_played: u64,
+    pub bump: u8,
+}
+
+/// Immutable audit record of how one round of a wager ended (`["receipt", wager,
+/// series_round]`). Written once at settlement and never closed, so it outlives the
+/// wager for disputes and analytics.
+#[account]
+#[derive(InitSpace)]
+pub struct SettlementReceipt {
+    pub wager: Pubkey,
+    pub series_round: u64,
+    pub status: WagerStatus,
+    pub winner: Option<u8>,
+    /// Paid (or held for claiming) to the winner; 0 without a winner
+    pub winner_amount: u64,
+    pub fee_amount: u64,
+    /// Returned to player 1 and player 2 by a refund or cancellation
+    pub refunds: [u64; 2],
+    /// The arbiter for declarations, otherwise whoever paid for the receipt
+    pub settled_by: Pubkey,
+    pub settled_at: i64,
+}
+
+/// Claims pool funded by the insurance leg of settlements, paid out by its authority
+#[account]
+#[derive(InitSpace)]
+pub struct InsurancePool {
+    pub authority: Pubkey,
+    /// Lamports routed into the pool by settlements
+    pub total_accrued: u64,
+    /// Lamports paid out of the pool by `insurance_payout`
+    pub total_paid: u64,
+    pub bump: u8,
+}
+
+/// Protocol revenue: fees of the wagers naming it as fee recipient accumulate in its
+/// lamports until the authority withdraws them
+#[account]
+#[derive(InitSpace)]
+pub struct FeeVault {
+    pub authority: Pubkey,
+    /// Lamports withdrawn by `withdraw_fees`
+    pub total_withdrawn: u64,
+    pub bump: u8,
+}
+
+/// Third-party backing of the two players of a wager (`["side_pool", wager]`). Stakes
+/// are held in the per-player side vaults; the outcome is copied from the wager by
+/// `settle_side_pool` so backers can still claim after the wager is closed.
+#[account]
+#[derive(InitSpace)]
+pub struct SidePool {
+    pub wager: Pubkey,
+    pub fee_recipient: Pubkey,
+    /// Lamports backing player 1 and player 2
+    pub total_backing: [u64; 2],
+    pub winner: Option<u8>,
+    pub is_settled: bool,
+    pub bump: u8,
+    pub vault_bumps: [u8; 2],
+}
+
+/// What a settled side bet pays out, from `SidePool::side_bet_payout`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SideBetPayout {
+    /// The bet itself, returned from the backed player's side vault
+    pub stake: u64,
+    /// Share of the losing side's pool, net of the fee
+    pub winnings: u64,
+    pub fee_amount: u64,
+}
+
+impl SidePool {
+    /// Payout of a bet of `amount` on `player`. Winning backers split the losing side's
+    /// pool pro rata, less the fee (rounded down). Stakes are simply returned when the
+    /// wager had no winner or nobody backed it.
+    pub fn side_bet_payout(&self, player: u8, amount: u64) -> Result<SideBetPayout> {
+        match self.winner {
+            Some(winner) if self.total_backing[(winner - 1) as usize] > 0 => {
+                if player != winner {
+                    return Ok(SideBetPayout::default());
+                }
+                let backed = self.total_backing[(player - 1) as usize] as u128;
+                let opposing = self.total_backing[(2 - player) as usize] as u128;
+                let share = opposing
+                    .checked_mul(amount as u128)
+                    .and_then(|product| product.checked_div(backed))
+                    .and_then(|share| u64::try_from(share).ok())
+                    .ok_or_else(|| error!(ErrorCode::Overflow))?;
+                let fee_amount = share
+                    .checked_mul(FEE_PERCENTAGE)
+                    .map(|fee| fee / 100)
+                    .ok_or_else(|| error!(ErrorCode::Overflow))?;
+                Ok(SideBetPayout {
+                    stake: amount,
+                    winnings: share - fee_amount,
+                    fee_amount,
+                })
+            }
+            _ => Ok(SideBetPayout {
+                stake: amount,
+                ..SideBetPayout::default()
+            }),
+        }
+    }
+}
+
+/// A backer's stake on one player of a wager (`["side_bet", wager, backer]`)
+#[account]
+#[derive(InitSpace)]
+pub struct SideBet {
+    pub wager: Pubkey,
+    pub backer: Pubkey,
+    /// Backed player: 1 or 2
+    pub player: u8,
+    pub amount: u64,
+    pub bump: u8,
+}
+
+/// Wagers a player has deposited into and that are not yet released (`["activity", player]`)
+#[account]
+#[derive(InitSpace)]
+pub struct PlayerActivity {
+    pub player: Pubkey,
+    /// Cap on `active_wagers`, at most `MAX_ACTIVE_GAMES`
+    pub max_active_games: u16,
+    #[max_len(MAX_ACTIVE_GAMES)]
+    pub active_wagers: Vec<Pubkey>,
+    pub bump: u8,
+}
+
+/// Lifecycle stage of a wager (`Wager::status`). The deposit and settlement state is
+/// derived from it, so the stages that follow a single deposit name the seat.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq, InitSpace)]
+pub enum WagerStatus {
+    /// Initialized; nobody has deposited yet
+    #[default]
+    Created,
+    /// Player 1 has deposited and player 2 has not yet matched
+    WaitingPlayer2,
+    /// Player 2 (e.g. the house) has deposited and player 1 has not yet matched
+    WaitingPlayer1,
+    /// Both players deposited; the match is running
+    Active,
+    /// A winner was declared
+    Settled,
+    /// Both stakes were returned without a winner (timeout refund or arbiter void)
+    Refunded,
+    /// Called off after only player 1 deposited; their stake was refunded
+    CancelledPlayer1Refunded,
+    /// Called off after only player 2 deposited; their stake was refunded
+    CancelledPlayer2Refunded,
+    /// Called off before anyone deposited
+    CancelledUnfunded,
 }
 
-#[derive(Accounts)]
-pub struct DepositPlayer2<'info> {
-    #[account(
-        mut,
-        seeds = [b"wager", wager.player1.as_ref(), wager.player2.as_ref()],
-        bump = wager.bump
-    )]
-    pub wager: Account<'info, Wager>,
-    /// CHECK: Vault PDA for holding SOL deposits
-    #[account(
-        mut,
-        seeds = [b"vault", wager.player1.as_ref(), wager.player2.as_ref()],
-        bump = wager.vault_bump
-    )]
-    pub vault: AccountInfo<'info>,
-    #[account(mut)]
-    pub player2: Signer<'info>,
-    pub system_program: Program<'info, System>,
+impl WagerStatus {
+    /// Moves the instructions make: deposits fill the seats, only an active game is
+    /// settled or refunded, and a one-sided cancellation only goes back to `Created`
+    /// through `reopen_wager`
+    pub fn can_transition_to(self, next: WagerStatus) -> bool {
+        use WagerStatus::*;
+        matches!(
+            (self, next),
+            (Created, WaitingPlayer2 | WaitingPlayer1 | CancelledUnfunded)
+                | (WaitingPlayer2, Active | CancelledPlayer1Refunded)
+                | (WaitingPlayer1, Active | CancelledPlayer2Refunded)
+                | (Active, Settled | Refunded)
+                | (CancelledPlayer1Refunded | CancelledPlayer2Refunded, Created)
+        )
+    }
+    
+    /// Player 1's stake has been deposited (and, once cancelled, refunded)
+    pub fn player1_deposited(self) -> bool {
+        use WagerStatus::*;
+        matches!(self, WaitingPlayer2 | Active | Settled | Refunded | CancelledPlayer1Refunded)
+    }
+    
+    /// Player 2's stake has been deposited (and, once cancelled, refunded)
+    pub fn player2_deposited(self) -> bool {
+        use WagerStatus::*;
+        matches!(self, WaitingPlayer1 | Active | Settled | Refunded | CancelledPlayer2Refunded)
+    }
+    
+    /// Any of the cancelled stages
+    pub fn is_cancelled(self) -> bool {
+        use WagerStatus::*;
+        matches!(self, CancelledPlayer1Refunded | CancelledPlayer2Refunded | CancelledUnfunded)
+    }
+    
+    /// The game is over: settled, refunded or cancelled
+    pub fn is_settled(self) -> bool {
+        matches!(self, WagerStatus::Settled | WagerStatus::Refunded) || self.is_cancelled()
+    }
+    
+    /// The cancelled stage a wager that is not fully funded moves to, recording whose
+    /// deposit was refunded
+    pub fn cancelled(self) -> WagerStatus {
+        match self {
+            WagerStatus::WaitingPlayer2 => WagerStatus::CancelledPlayer1Refunded,
+            WagerStatus::WaitingPlayer1 => WagerStatus::CancelledPlayer2Refunded,
+            _ => WagerStatus::CancelledUnfunded,
+        }
+    }
 }
 
-#[derive(Accounts)]
-pub struct DeclareWinner<'info> {
-    #[account(
-        mut,
-        seeds = [b"wager", wager.player1.as_ref(), wager.player2.as_ref()],
-        bump = wager.bump
-    )]
-    pub wager: Account<'info, Wager>,
-    /// CHECK: Vault PDA for holding SOL deposits
-    #[account(
-        mut,
-        seeds = [b"vault", wager.player1.as_ref(), wager.player2.as_ref()],
-        bump = wager.vault_bump
-    )]
-    pub vault: AccountInfo<'info>,
-    pub arbiter: Signer<'info>,
-    /// CHECK: This is the winner account (either player1 or player2)
-    #[account(mut)]
-    pub winner_account: AccountInfo<'info>,
-    /// CHECK: This is the fee recipient account
-    #[account(mut)]
-    pub fee_recipient: AccountInfo<'info>,
-    pub system_program: Program<'info, System>,
+/// Routes `insurance_bps` of the fee to the insurance pool at `pool`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, InitSpace)]
+pub struct InsuranceLeg {
+    pub pool: Pubkey,
+    /// Share of the fee routed to the pool (basis points)
+    pub insurance_bps: u16,
 }
 
-#[derive(Accounts)]
-pub struct Refund<'info> {
-    #[account(
-        mut,
-        seeds = [b"wager", wager.player1.as_ref(), wager.player2.as_ref()],
-        bump = wager.bump
-    )]
-    pub wager: Account<'info, Wager>,
-    /// CHECK: Vault PDA for holding SOL deposits
-    #[account(
-        mut,
-        seeds = [b"vault", wager.player1.as_ref(), wager.player2.as_ref()],
-        bump = wager.vault_bump
-    )]
-    pub vault: AccountInfo<'info>,
-    /// CHECK: Player 1 account for refund
-    #[account(mut)]
-    pub player1: AccountInfo<'info>,
-    /// CHECK: Player 2 account for refund
-    #[account(mut)]
-    pub player2: AccountInfo<'info>,
-    pub system_program: Program<'info, System>,
+/// Waives part of the fee while the arbiter has settled fewer than `matches` matches
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, InitSpace)]
+pub struct FeeWaiver {
+    pub matches: u64,
+    /// Share of the fee waived in favour of the winner (basis points)
+    pub waiver_bps: u16,
 }
 
-#[derive(Accounts)]
-pub struct CancelWager<'info> {
-    #[account(
-        mut,
-        seeds = [b"wager", wager.player1.as_ref(), wager.player2.as_ref()],
-        bump = wager.bump
-    )]
-    pub wager: Account<'info, Wager>,
-    /// CHECK: Vault PDA for holding SOL deposits
-    #[account(
-        mut,
-        seeds = [b"vault", wager.player1.as_ref(), wager.player2.as_ref()],
-        bump = wager.vault_bump
-    )]
-    pub vault: AccountInfo<'info>,
-    /// CHECK: Player 1 account for refund
-    #[account(mut)]
-    pub player1: AccountInfo<'info>,
-    /// CHECK: Player 2 account for refund
-    #[account(mut)]
-    pub player2: AccountInfo<'info>,
-    pub system_program: Program<'info, System>,
+/// Splits the fee between the wager's `fee_recipient` and a secondary recipient (e.g. a referrer)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, InitSpace)]
+pub struct FeeSplit {
+    pub secondary_recipient: Pubkey,
+    /// Share of the fee paid to `fee_recipient` (basis points)
+    pub primary_bps: u16,
+    /// Share of the fee paid to `secondary_recipient` (basis points)
+    pub secondary_bps: u16,
 }
 
-#[account]
-#[derive(InitSpace)]
-pub struct Wager {
+/// Per-wager settings chosen at initialization
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct WagerOptions {
+    /// Share of leftover vault dust returned to the payer on close (basis points)
+    pub payer_dust_bps: u16,
+    /// Deduct the PDA rent from the players' pool instead of having the payer absorb it
+    pub deduct_init_cost_from_pool: bool,
+    /// Unix timestamp the match timer starts at, even if both deposits land earlier (0 = start on second deposit)
+    pub scheduled_start: i64,
+    /// Pay any vault balance above principal and rent (e.g. yield credited by an external
+    /// yield source while the match runs) to the winner at settlement
+    pub pay_yield_to_winner: bool,
+    /// Matches declared within this many seconds of start earn the winner a fee rebate (0 = disabled)
+    pub fast_threshold_seconds: i64,
+    /// Share of the fee rebated to the winner of a fast match (basis points)
+    pub fast_rebate_bps: u16,
+    /// Shared ID for all games in a tournament bracket, included in events for indexing
+    pub tournament_id: Option<[u8; 16]>,
+    /// Series play: round N is arbitrated by `arbiter_rotation[N % len]`, overriding `arbiter`
+    /// (N = wagers this pair has completed and closed). Empty = always use `arbiter`.
+    pub arbiter_rotation: Vec<Pubkey>,
+    /// Reject the wager if its nominal final deadline falls more than this many seconds
+    /// after the start of the current cluster epoch (0 = no limit)
+    pub max_lifetime_seconds: i64,
+    /// Split the fee with a secondary recipient; the shares must sum to 10000 basis points
+    pub fee_split: Option<FeeSplit>,
+    /// Hold the winner's share in the vault for `claim_winnings` instead of paying it at
+    /// declaration; once this many seconds pass it may be swept (0 = pay immediately)
+    pub claim_window_seconds: i64,
+    /// Reject winner declarations made less than this many seconds after the match starts
+    /// (0 = no minimum); at most the match timeout
+    pub min_match_seconds: i64,
+    /// Onboarding incentive: waive part of the fee for the arbiter's first matches, as
+    /// counted by their `ArbiterStats` (which must then be passed on declaration)
+    pub fee_waiver: Option<FeeWaiver>,
+    /// Links the wager to an off-chain match ID / lobby code (e.g. a hash or zero-padded
+    /// string) so front-ends can find it from their game server record
+    pub game_ref: [u8; 32],
+    /// Route a share of the fee to an insurance pool that backstops future shortfalls
+    pub insurance: Option<InsuranceLeg>,
+    /// Start the match timer this many seconds after the second deposit, giving both
+    /// clients a synchronized heads-up (0 = start immediately); at most 60 seconds
+    pub start_delay_seconds: i64,
+    /// Compliance hold (e.g. AML/fraud checks): when set, this key must co-sign every
+    /// settlement that pays out a winner, in addition to the arbiter or players
+    pub escrow_authority: Option<Pubkey>,
+    /// Onboarding incentive for players: waive part of the fee while either player has
+    /// fewer than `matches` games in their `PlayerStats` (both must then be passed on
+    /// declaration)
+    pub new_player_waiver: Option<FeeWaiver>,
+    /// Compensation for the arbiter (basis points of the pool, after the initialization
+    /// cost), paid out of the winner's share whenever the arbiter declares the result.
+    /// Together with the platform fee at most `MAX_COMBINED_FEE_BPS`.
+    pub arbiter_fee_bps: u16,
+    /// Which side gets the lamports lost to truncation when splitting the pool: `true`
+    /// rounds the fee down so the winner keeps the remainder, `false` rounds the winner's
+    /// 95% share down so the fee recipient gets it
+    pub round_fee_down: bool,
+    /// Reject `declare_winner` / `reveal_result` when another instruction in the same
+    /// transaction touches the wager, vault or winner account
+    pub strict_isolation: bool,
+    /// Close the wager and sweep the vault at the end of `declare_winner`, `refund` and
+    /// `cancel_wager` (and their variants) instead of waiting for `close_wager`
+    pub close_on_settle: bool,
+    /// Reward for the keeper of a `crank_refund` (basis points of the refunded pool,
+    /// taken equally from both players' refunds; at most `MAX_KEEPER_REWARD_BPS`)
+    pub keeper_reward_bps: u16,
+    /// Reject a fee recipient that is one of the players, at initialization and on
+    /// `update_fee_recipient` or `join_wager`, so nobody collects the fee on their own
+    /// wins. Off by default, e.g. for house wagers where the house takes the fee.
+    pub strict_fee_recipient: bool,
+    /// Accumulate the fees in the `FeeVault` of this authority instead of paying them to
+    /// a wallet; the fee recipient must then be that fee vault (`["fee_vault", authority]`)
+    pub fee_vault_authority: Option<Pubkey>,
+    /// Start the match timer only after both players confirm with `ready_up` (or the
+    /// ready timeout passes), rather than on the second deposit
+    pub require_ready: bool,
+    /// Require both players to pass their `PlayerActivity` when depositing, so the
+    /// wager always counts against their open-wager caps
+    pub cap_active_games: bool,
+}
+
+/// Returned by `preview_payout`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct PayoutPreview {
+    pub winner_amount: u64,
+    pub fee_amount: u64,
+    /// Initialization cost charged to the players' pool (0 when the payer absorbs it)
+    pub initialization_cost: u64,
+    pub arbiter_fee: u64,
+}
+
+/// Returned by `declare_winner`: the amounts actually paid, matching `WinnerDeclared`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct SettlementResult {
+    pub winner: u8,
+    /// Including any fast-match rebate, fee waiver and yield; held in the vault rather
+    /// than paid when the wager has a claim window
+    pub winner_amount: u64,
+    pub fee_amount: u64,
+}
+
+#[event]
+pub struct WagerInitialized {
+    pub wager: Pubkey,
     pub player1: Pubkey,
     pub player2: Pubkey,
     pub arbiter: Pubkey,
     pub fee_recipient: Pubkey,
     pub wager_amount: u64,
-    pub player1_deposited: bool,
-    pub player2_deposited: bool,
-    pub creation_time: i64,
-    pub start_time: i64,
-    pub winner: Option<u8>,
-    pub is_settled: bool,
-    pub bump: u8,
-    pub vault_bump: u8,
-    pub initialization_cost: u64,
+    pub tournament_id: Option<[u8; 16]>,
+    pub game_ref: [u8; 32],
+}
+
+#[event]
+pub struct WinnerDeclared {
+    pub wager: Pubkey,
+    pub winner: u8,
+    pub winner_amount: u64,
+    pub fee_amount: u64,
+    pub tournament_id: Option<[u8; 16]>,
+    /// Net change for each player (payout minus deposit): the winner's gain, the loser's loss
+    pub player1_net: i64,
+    pub player2_net: i64,
+}
+
+/// Emitted when stakes are returned without a winner (timeout refund, arbiter void or
+/// cancellation)
+#[event]
+pub struct WagerStatusChanged {
+    pub wager: Pubkey,
+    pub status: WagerStatus,
+}
+
+#[event]
+pub struct WagerRefunded {
+    pub wager: Pubkey,
+    /// Net change for each player (refund minus deposit); negative by any
+    /// initialization cost deducted from the pool, zero for a player who never deposited
+    pub player1_net: i64,
+    pub player2_net: i64,
+    pub tournament_id: Option<[u8; 16]>,
 }
 
 #[error_code]
@@ -446,7 +5180,7 @@ pub enum ErrorCode {
     UnauthorizedPlayer,
     #[msg("Wager has already been settled")]
     WagerAlreadySettled,
-    #[msg("Both players must deposit before declaring winner or refunding")]
+    #[msg("Both players must deposit before declaring winner or refunding; the match has not started")]
     BothPlayersNotDeposited,
     #[msg("Unauthorized arbiter")]
     UnauthorizedArbiter,
@@ -460,5 +5194,772 @@ pub enum ErrorCode {
     BothPlayersAlreadyDeposited,
     #[msg("Deposit timeout has not expired yet, cannot cancel")]
     DepositTimeoutNotExpired,
+    #[msg("Payer dust share must be at most 10000 basis points")]
+    InvalidDustSplit,
+    #[msg("Wager must be settled before it can be closed")]
+    WagerNotSettled,
+    #[msg("Account does not match the wager's original payer")]
+    InvalidPayer,
+    #[msg("Account does not match the wager's fee recipient")]
+    InvalidFeeRecipient,
+    #[msg("Match has not started yet (timer starts when both players deposit)")]
+    MatchNotStarted,
+    #[msg("Scheduled start must be in the future (or 0 to start on the second deposit)")]
+    InvalidScheduledStart,
+    #[msg("Too many wagers initialized for this player pair this epoch")]
+    PairInitializationCapReached,
+    #[msg("Vault still holds lamports after the closing sweep")]
+    VaultNotEmpty,
+    #[msg("Winner account does not match the winner's payout address")]
+    InvalidWinnerAccount,
+    #[msg("Arbiter cannot be changed after both players have deposited")]
+    CannotChangeArbiterAfterStart,
+    #[msg("Arbiter can only be changed by the current arbiter or both players together")]
+    UnauthorizedArbiterChange,
+    #[msg("Fast match threshold must be non-negative and rebate at most 10000 basis points")]
+    InvalidFastRebate,
+    #[msg("Arbiter rotation can hold at most 4 arbiters")]
+    ArbiterRotationTooLong,
+    #[msg("Vault balance is below the expected pool plus rent reserve")]
+    InsufficientVaultBalance,
+    #[msg("Payout address cannot be the system program, a sysvar, or this program")]
+    ReservedPayoutAddress,
+    #[msg("Wager deadlines would extend beyond the configured maximum lifetime")]
+    WagerLifetimeExceeded,
+    #[msg("Wager amount cannot be changed after a player has deposited")]
+    CannotChangeAmountAfterDeposit,
+    #[msg("Wager amount can only be changed by the payer or both players together")]
+    UnauthorizedAmountChange,
+    #[msg("Fee split shares must sum to 10000 basis points")]
+    InvalidFeeSplit,
+    #[msg("Secondary fee recipient is missing or does not match the wager's fee split")]
+    InvalidSecondaryFeeRecipient,
+    #[msg("A player has deposited; use cancel_wager to refund instead")]
+    WagerNotAbandoned,
+    #[msg("Revealed winner and salt do not match the committed result")]
+    CommitmentMismatch,
+    #[msg("A result is already committed; settle it with reveal_result")]
+    ResultAlreadyCommitted,
+    #[msg("No result has been committed")]
+    NoResultCommitted,
+    #[msg("Players picked different winners")]
+    PlayersDisagree,
+    #[msg("Claim window must be non-negative")]
+    InvalidClaimWindow,
+    #[msg("No unclaimed winnings are held for this wager")]
+    NothingToClaim,
+    #[msg("Claim window is still open for the winner")]
+    ClaimStillOpen,
+    #[msg("Only the fee recipient or the payer can sweep unclaimed winnings")]
+    UnauthorizedSweep,
+    #[msg("Winnings must be claimed or swept before the wager is closed")]
+    WinningsUnclaimed,
+    #[msg("Minimum match duration must be between 0 and the match timeout")]
+    InvalidMinMatchDuration,
+    #[msg("Match has not lasted the minimum duration yet, cannot declare winner")]
+    MatchTooShort,
+    #[msg("Fee waiver must be at most 10000 basis points")]
+    InvalidFeeWaiver,
+    #[msg("Arbiter stats account is required when the wager has a fee waiver")]
+    InvalidArbiterStats,
+    #[msg("Insurance share must be at most 10000 basis points")]
+    InvalidInsuranceLeg,
+    #[msg("Insurance pool is missing or does not match the wager's insurance leg")]
+    InvalidInsurancePool,
+    #[msg("Insurance pool cannot pay more than its balance above the rent reserve")]
+    InsufficientInsuranceFunds,
+    #[msg("Only the insurance pool authority can pay out claims")]
+    UnauthorizedInsuranceAuthority,
+    #[msg("Vault does not match the canonical vault PDA for this wager")]
+    InvalidVaultBump,
+    #[msg("Start delay must be between 0 and 60 seconds")]
+    InvalidStartDelay,
+    #[msg("The wager's escrow authority must co-sign this settlement")]
+    MissingEscrowSignature,
+    #[msg("Wager is already on the current layout version")]
+    WagerAlreadyMigrated,
+    #[msg("Side bets are closed for this wager")]
+    SideBetsClosed,
+    #[msg("Side bet amount must be greater than 0")]
+    InvalidSideBetAmount,
+    #[msg("Side pool has already been settled")]
+    SidePoolAlreadySettled,
+    #[msg("Side pool has not been settled yet")]
+    SidePoolNotSettled,
+    #[msg("Settle the wager's side pool before closing it")]
+    SidePoolUnsettled,
+    #[msg("Winner payout would be zero after the initialization cost and fee")]
+    ZeroWinnerPayout,
+    #[msg("Only a player or the payer can start a rematch")]
+    UnauthorizedRematch,
+    #[msg("Too many open wagers for this player; settle one and release it first")]
+    TooManyActiveGames,
+    #[msg("Active game cap must be between 1 and 8")]
+    InvalidActiveGamesCap,
+    #[msg("Wager is not counted as an open game for this player")]
+    GameNotActive,
+    #[msg("Result is not signed by the arbiter for this wager and nonce")]
+    InvalidSignedResult,
+    #[msg("Invalid wager status transition")]
+    InvalidStatusTransition,
+    #[msg("This player pair is still in its initialization cooldown")]
+    InitCooldown,
+    #[msg("Initialization cooldown must be between 0 and 86400 seconds")]
+    InvalidInitCooldown,
+    #[msg("Winner account would not be rent exempt after the payout")]
+    WinnerAccountNotViable,
+    #[msg("Program is running under a different id than it was built with")]
+    ProgramIdMismatch,
+    #[msg("Cancelling before the deposit timeout requires both players' signatures")]
+    MutualCancelRequiresConsent,
+    #[msg("Wager amount does not cover the initialization cost charged to it")]
+    WagerTooSmallForRent,
+    #[msg("Both players' stats accounts are required when the wager has a new-player fee waiver")]
+    InvalidPlayerStats,
+    #[msg("Arbiter fee plus the platform fee exceeds the maximum combined fee")]
+    CombinedFeeTooHigh,
+    #[msg("Vault balance did not grow by exactly the deposited amount")]
+    DepositAmountMismatch,
+    #[msg("Team shares must be positive, sum to 10000 basis points and match the member accounts")]
+    InvalidTeamShares,
+    #[msg("Wager has not expired yet")]
+    WagerNotExpired,
+    #[msg("Match has already started")]
+    MatchAlreadyStarted,
+    #[msg("Someone has already joined this wager as player 2")]
+    WagerAlreadyJoined,
+    #[msg("Only the current fee recipient can change the fee recipient")]
+    UnauthorizedFeeRecipient,
+    #[msg("Vault holds less than the amount being paid out")]
+    VaultUnderflow,
+    #[msg("The arbiter has already been changed the maximum number of times")]
+    TooManyArbiterChanges,
+    #[msg("Wager has not been cancelled")]
+    WagerNotCancelled,
+    #[msg("Wager is not a house wager")]
+    NotHouseWager,
+    #[msg("The house must deposit its stake before the player")]
+    HouseStakeMissing,
+    #[msg("The instructions sysvar is required to settle a wager with strict isolation")]
+    MissingInstructionsSysvar,
+    #[msg("Another instruction in the transaction touches the wager, vault or winner account")]
+    InstructionNotIsolated,
+    #[msg("Closing on settlement requires the payer, fee recipient and pair stats accounts")]
+    CloseAccountsMissing,
+    #[msg("A drawn result requires both player accounts")]
+    DrawAccountsMissing,
+    #[msg("Keeper reward cannot exceed 100 basis points")]
+    KeeperRewardTooHigh,
+    #[msg("Only a wager cancelled with exactly one deposit can be reopened")]
+    NotReopenable,
+    #[msg("The window to reopen this cancelled wager has passed")]
+    ReopenWindowExpired,
+    #[msg("The fee recipient cannot be a player of this wager")]
+    FeeRecipientIsPlayer,
+    #[msg("Fee recipient must be the fee vault of the configured authority")]
+    InvalidFeeVault,
+    #[msg("Fee vault cannot pay more than its balance above the rent reserve")]
+    InsufficientFeeVaultFunds,
+    #[msg("Only the fee vault authority can withdraw fees")]
+    UnauthorizedFeeVaultAuthority,
+    #[msg("Wager does not use the ready handshake")]
+    ReadyNotRequired,
+    #[msg("The stakes add up to more than a pool can hold")]
+    PoolOverflow,
+    #[msg("A batch settles 1 to 4 matches, each with a writable wager, vault and winner account")]
+    InvalidBatch,
+    #[msg("Voiding a game without the arbiter requires both players' signatures")]
+    MutualVoidRequiresBothSignatures,
+    #[msg("This wager caps open games: deposits must pass the player's activity account")]
+    PlayerActivityRequired,
+    #[msg("The opponent has not deposited yet; cancel the wager instead of conceding")]
+    OpponentNotDeposited,
+    #[msg("This wager needs accounts a batch cannot pass; declare its winner on its own")]
+    WagerNotBatchable,
+    #[msg("The arbiter has frozen settlements on this wager; only refunds and voids are open")]
+    SettlementsFrozen,
+    #[msg("Arithmetic overflow")]
+    Overflow,
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fee_rounded_down() -> Wager {
+        Wager { round_fee_down: true, ..Wager::default() }
+    }
+
+    #[test]
+    fn split_rounds_the_fee_down_in_favour_of_the_winner() {
+        for pool in [1u64, 2, 19, 21, 39, 99, 101, 1_999_999_999] {
+            let (winner_amount, fee_amount) = fee_rounded_down().split_pool(pool);
+            assert_eq!(fee_amount, pool * FEE_PERCENTAGE / 100);
+            assert_eq!(winner_amount + fee_amount, pool);
+            // Never less than the truncated 95% share
+            assert!(winner_amount >= pool * WINNER_PERCENTAGE / 100);
+        }
+    }
+
+    #[test]
+    fn split_never_zeroes_the_winner_of_a_non_empty_pool() {
+        // At the 5% fee ceiling even a 1-lamport pool pays the winner
+        for pool in 1u64..=100 {
+            let (winner_amount, _) = fee_rounded_down().split_pool(pool);
+            assert!(winner_amount > 0, "pool {pool}");
+        }
+    }
+
+    #[test]
+    fn split_gives_the_winner_the_remainder_of_an_odd_pool() {
+        // 5% of 39 is 1.95: the fee is 1 and the winner keeps 38 rather than floor(37.05) = 37
+        assert_eq!(fee_rounded_down().split_pool(39), (38, 1));
+        // Below 20 lamports the fee truncates to zero
+        assert_eq!(fee_rounded_down().split_pool(19), (19, 0));
+        assert_eq!(fee_rounded_down().split_pool(20), (19, 1));
+    }
+
+    #[test]
+    fn split_gives_the_fee_recipient_the_remainder_when_rounding_up() {
+        // 95% of 39 is 37.05: the winner gets 37 and the fee recipient 2
+        assert_eq!(Wager::default().split_pool(39), (37, 2));
+        assert_eq!(Wager::default().split_pool(19), (18, 1));
+        // No remainder: both modes agree
+        assert_eq!(Wager::default().split_pool(20), fee_rounded_down().split_pool(20));
+        for pool in [1u64, 2, 19, 21, 39, 99, 101, 1_999_999_999] {
+            let (winner_amount, fee_amount) = Wager::default().split_pool(pool);
+            assert_eq!(winner_amount, pool * WINNER_PERCENTAGE / 100);
+            assert_eq!(winner_amount + fee_amount, pool);
+        }
+    }
+
+    #[test]
+    fn net_changes_for_winner_and_loser() {
+        let wager = Wager {
+            wager_amount: 1_000,
+            status: WagerStatus::Active,
+            ..Wager::default()
+        };
+        assert_eq!(wager.net_changes(1, 1_900), (900, -1_000));
+        assert_eq!(wager.net_changes(2, 1_900), (-1_000, 900));
+    }
+
+    #[test]
+    fn net_changes_when_the_winner_never_deposited() {
+        // A player who never deposited gains their payout outright
+        let wager = Wager {
+            wager_amount: 1_000,
+            status: WagerStatus::WaitingPlayer2,
+            ..Wager::default()
+        };
+        assert_eq!(wager.net_changes(2, 950), (-1_000, 950));
+    }
+
+    #[test]
+    fn side_bet_winners_split_the_losing_pool_pro_rata() {
+        let side_pool = SidePool {
+            wager: Pubkey::default(),
+            fee_recipient: Pubkey::default(),
+            total_backing: [400, 200],
+            winner: Some(1),
+            is_settled: true,
+            bump: 0,
+            vault_bumps: [0, 0],
+        };
+        // A quarter of the winning side earns a quarter of the 200 losing lamports, less 5%
+        let payout = |stake, winnings, fee_amount| SideBetPayout { stake, winnings, fee_amount };
+        assert_eq!(side_pool.side_bet_payout(1, 100).unwrap(), payout(100, 48, 2));
+        assert_eq!(side_pool.side_bet_payout(1, 300).unwrap(), payout(300, 143, 7));
+        assert_eq!(side_pool.side_bet_payout(2, 200).unwrap(), SideBetPayout::default());
+    }
+
+    #[test]
+    fn side_bet_payouts_report_overflow_instead_of_panicking() {
+        let side_pool = SidePool {
+            wager: Pubkey::default(),
+            fee_recipient: Pubkey::default(),
+            total_backing: [1, u64::MAX],
+            winner: Some(1),
+            is_settled: true,
+            bump: 0,
+            vault_bumps: [0, 0],
+        };
+        // The whole losing pool fits a u64, but its fee does not
+        assert!(side_pool.side_bet_payout(1, 1).is_err());
+    }
+
+    #[test]
+    fn side_bets_are_refunded_without_a_backed_winner() {
+        let mut side_pool = SidePool {
+            wager: Pubkey::default(),
+            fee_recipient: Pubkey::default(),
+            total_backing: [0, 200],
+            winner: Some(1),
+            is_settled: true,
+            bump: 0,
+            vault_bumps: [0, 0],
+        };
+        let refund = SideBetPayout { stake: 200, winnings: 0, fee_amount: 0 };
+        assert_eq!(side_pool.side_bet_payout(2, 200).unwrap(), refund);
+        side_pool.winner = None;
+        assert_eq!(side_pool.side_bet_payout(2, 200).unwrap(), refund);
+    }
+
+    /// Ed25519 program data for one signature with everything inline, laid out as the
+    /// web3.js `Ed25519Program` helper does: public key, signature, then message
+    fn ed25519_data(public_key: &Pubkey, message: &[u8]) -> Vec<u8> {
+        let (public_key_offset, signature_offset, message_offset) = (16u16, 48u16, 112u16);
+        let mut data = vec![1, 0];
+        for field in [
+            signature_offset,
+            u16::MAX,
+            public_key_offset,
+            u16::MAX,
+            message_offset,
+            message.len() as u16,
+            u16::MAX,
+        ] {
+            data.extend_from_slice(&field.to_le_bytes());
+        }
+        data.extend_from_slice(public_key.as_ref());
+        data.extend_from_slice(&[0u8; 64]);
+        data.extend_from_slice(message);
+        data
+    }
+
+    #[test]
+    fn team_payouts_split_by_share_with_the_remainder_to_the_first_member() {
+        let payouts = team_payouts(1_000_001, &[5000, 3000, 2000]).unwrap();
+        assert_eq!(payouts, vec![500_001, 300_000, 200_000]);
+        assert_eq!(payouts.iter().sum::<u64>(), 1_000_001);
+
+        assert!(team_payouts(1_000, &[]).is_err());
+        assert!(team_payouts(1_000, &[5000, 4999]).is_err());
+        assert!(team_payouts(1_000, &[10000, 0]).is_err());
+        assert!(team_payouts(1_000, &[1250; MAX_TEAM_MEMBERS]).is_ok());
+        assert!(team_payouts(1_000, &[1000; MAX_TEAM_MEMBERS + 2]).is_err());
+    }
+
+    #[test]
+    fn refund_follows_the_init_cost_policy() {
+        let mut wager = Wager {
+            wager_amount: 100_000_000,
+            initialization_cost: 2_000_001,
+            deduct_init_cost_from_pool: true,
+            ..Wager::default()
+        };
+        assert_eq!(wager.refund_amount_each().unwrap(), 100_000_000 - 1_000_001);
+
+        wager.deduct_init_cost_from_pool = false;
+        assert_eq!(wager.refund_amount_each().unwrap(), 100_000_000);
+    }
+
+    #[test]
+    fn participants_are_the_players_arbiter_and_fee_recipient() {
+        let [player1, player2, arbiter, fee_recipient, outsider] =
+            [0; 5].map(|_| Pubkey::new_unique());
+        let wager = Wager { player1, player2, arbiter, fee_recipient, ..Wager::default() };
+
+        assert_eq!(wager.player_number(&player1), Some(1));
+        assert_eq!(wager.player_number(&player2), Some(2));
+        assert_eq!(wager.player_number(&arbiter), None);
+        for key in [player1, player2, arbiter, fee_recipient] {
+            assert!(wager.is_participant(&key));
+        }
+        assert!(!wager.is_participant(&outsider));
+
+        assert!(wager.are_both_players(&player1, Some(&player2)));
+        assert!(wager.are_both_players(&player2, Some(&player1)));
+        assert!(!wager.are_both_players(&player1, Some(&player1)));
+        assert!(!wager.are_both_players(&player1, Some(&arbiter)));
+        assert!(!wager.are_both_players(&player1, None));
+    }
+
+    #[test]
+    fn only_wagers_without_optional_declaration_accounts_are_batchable() {
+        assert!(Wager::default().is_batchable());
+        assert!(!Wager { close_on_settle: true, ..Wager::default() }.is_batchable());
+        assert!(!Wager { strict_isolation: true, ..Wager::default() }.is_batchable());
+        assert!(!Wager { escrow_authority: Some(Pubkey::new_unique()), ..Wager::default() }.is_batchable());
+    }
+
+    #[test]
+    fn an_open_seat_matches_nobody() {
+        let player1 = Pubkey::new_unique();
+        let wager = Wager { player1, player2: Pubkey::default(), ..Wager::default() };
+
+        assert_eq!(wager.player_number(&Pubkey::default()), None);
+        assert!(wager.is_participant(&player1));
+    }
+
+    #[test]
+    fn sysvars_and_programs_are_reserved_payout_addresses() {
+        use anchor_lang::solana_program::sysvar;
+        assert!(is_reserved_address(&sysvar::rent::ID));
+        assert!(is_reserved_address(&sysvar::instructions::ID));
+        assert!(is_reserved_address(&sysvar::ID));
+        assert!(is_reserved_address(&anchor_lang::system_program::ID));
+        assert!(is_reserved_address(&crate::ID));
+        assert!(!is_reserved_address(&Pubkey::new_unique()));
+    }
+
+    #[test]
+    fn total_pool_sums_both_stakes() {
+        let wager = Wager { wager_amount: 100_000_000, ..Wager::default() };
+        assert_eq!(wager.total_pool().unwrap(), 200_000_000);
+
+        // The largest stake that still fits twice
+        let wager = Wager { wager_amount: u64::MAX / 2, ..Wager::default() };
+        assert_eq!(wager.total_pool().unwrap(), u64::MAX - 1);
+        let wager = Wager { wager_amount: u64::MAX / 2 + 1, ..Wager::default() };
+        assert!(wager.total_pool().is_err());
+    }
+
+    #[test]
+    fn principal_counts_only_the_deposited_stakes() {
+        let mut wager = Wager { wager_amount: 100_000_000, ..Wager::default() };
+        assert_eq!(wager.principal().unwrap(), 0);
+        wager.status = WagerStatus::WaitingPlayer1;
+        assert_eq!(wager.principal().unwrap(), 100_000_000);
+        wager.status = WagerStatus::Active;
+        assert_eq!(wager.principal().unwrap(), 200_000_000);
+
+        // Stakes too large for one pool fail rather than panic
+        wager.wager_amount = u64::MAX / 2 + 1;
+        assert!(wager.principal().is_err());
+        assert!(wager.refund_amount_each().is_err());
+        assert!(wager.keeper_reward_each().is_err());
+    }
+
+    #[test]
+    fn sum_stakes_handles_asymmetric_stakes() {
+        assert_eq!(sum_stakes([100, 300]).unwrap(), 400);
+        assert_eq!(sum_stakes([0, 300]).unwrap(), 300);
+        assert_eq!(sum_stakes([u64::MAX - 300, 300]).unwrap(), u64::MAX);
+        assert!(sum_stakes([u64::MAX - 299, 300]).is_err());
+        assert!(sum_stakes([u64::MAX, 1]).is_err());
+    }
+
+    #[test]
+    fn keeper_reward_is_a_cut_of_each_refund() {
+        let mut wager = Wager { wager_amount: 100_000_000, ..Wager::default() };
+        assert_eq!(wager.keeper_reward_each().unwrap(), 0);
+
+        wager.keeper_reward_bps = 10;
+        assert_eq!(wager.keeper_reward_each().unwrap(), 100_000);
+    }
+
+    #[test]
+    fn keeper_tip_is_capped_by_the_fee_on_each_refund() {
+        let wager = Wager { wager_amount: 100_000_000, ..Wager::default() };
+        assert_eq!(wager.keeper_tip_each().unwrap(), KEEPER_TIP_LAMPORTS / 2);
+
+        // A 5% fee on a 20_000 lamport refund is only 1_000
+        let wager = Wager { wager_amount: 20_000, ..Wager::default() };
+        assert_eq!(wager.keeper_tip_each().unwrap(), 1_000);
+    }
+
+    #[test]
+    fn stake_must_exceed_the_init_cost_charged_to_it() {
+        assert!(require_stake_covers_init_cost(2_000_002, 2_000_001).is_ok());
+        assert!(require_stake_covers_init_cost(2_000_001, 2_000_001).is_err());
+        assert!(require_stake_covers_init_cost(1_000_001, 2_000_001).is_err());
+        // Nothing to cover when the payer absorbs the cost
+        assert!(require_stake_covers_init_cost(1, 0).is_ok());
+    }
+
+    #[test]
+    fn derives_the_status_of_a_v1_wager_from_its_flags() {
+        let mut wager = Wager::default();
+        assert_eq!(wager.legacy_status(), WagerStatus::Created);
+        wager.legacy_player2_deposited = true;
+        assert_eq!(wager.legacy_status(), WagerStatus::WaitingPlayer1);
+        wager.legacy_player1_deposited = true;
+        assert_eq!(wager.legacy_status(), WagerStatus::Active);
+        wager.legacy_is_settled = true;
+        assert_eq!(wager.legacy_status(), WagerStatus::Refunded);
+        wager.winner = Some(1);
+        assert_eq!(wager.legacy_status(), WagerStatus::Settled);
+
+        let cancelled = Wager {
+            legacy_player1_deposited: true,
+            legacy_is_settled: true,
+            ..Wager::default()
+        };
+        assert_eq!(cancelled.legacy_status(), WagerStatus::CancelledPlayer1Refunded);
+    }
+
+    #[test]
+    fn the_status_implies_the_flags_it_is_derived_from() {
+        use WagerStatus::*;
+        for status in [
+            Created,
+            WaitingPlayer2,
+            WaitingPlayer1,
+            Active,
+            Refunded,
+            CancelledPlayer1Refunded,
+            CancelledUnfunded,
+            CancelledPlayer2Refunded,
+        ] {
+            let wager = Wager {
+                legacy_player1_deposited: status.player1_deposited(),
+                legacy_player2_deposited: status.player2_deposited(),
+                legacy_is_settled: status.is_settled(),
+                ..Wager::default()
+            };
+            assert_eq!(wager.legacy_status(), status);
+        }
+        // A cancellation records whose deposit it refunded
+        assert_eq!(WaitingPlayer2.cancelled(), CancelledPlayer1Refunded);
+        assert_eq!(WaitingPlayer1.cancelled(), CancelledPlayer2Refunded);
+        assert_eq!(Created.cancelled(), CancelledUnfunded);
+    }
+
+    #[test]
+    fn only_an_unsettled_wager_past_its_expiry_can_be_voided() {
+        let mut wager = Wager {
+            expiry_time: 1_000,
+            ..Wager::default()
+        };
+        assert!(!wager.is_expired(1_000));
+        assert!(wager.is_expired(1_001));
+        assert_ne!(wager.available_actions(1_001) & ACTION_VOID_EXPIRED, 0);
+        wager.status = WagerStatus::CancelledUnfunded;
+        assert_eq!(wager.available_actions(1_001) & ACTION_VOID_EXPIRED, 0);
+        // A zero expiry never passes
+        assert!(!Wager::default().is_expired(i64::MAX));
+    }
+
+    #[test]
+    fn an_open_challenge_offers_its_seat_instead_of_the_player2_deposit() {
+        let open = Wager {
+            pda_seed: open_wager_seed(7),
+            ..Wager::default()
+        };
+        let actions = open.available_actions(0);
+        assert_ne!(actions & ACTION_JOIN_WAGER, 0);
+        assert_eq!(actions & ACTION_DEPOSIT_PLAYER2, 0);
+
+        let joined = Wager {
+            player2: Pubkey::new_unique(),
+            ..open
+        };
+        assert_eq!(joined.available_actions(0) & ACTION_JOIN_WAGER, 0);
+        assert_ne!(open_wager_seed(7), open_wager_seed(8));
+    }
+
+    #[test]
+    fn a_house_wager_offers_the_player_deposit_once_the_house_has_funded() {
+        let house = Wager {
+            house_wager: true,
+            player2: Pubkey::new_unique(),
+            ..Wager::default()
+        };
+        assert_eq!(house.available_actions(0) & ACTION_DEPOSIT_PLAYER1, 0);
+        assert_ne!(house.available_actions(0) & ACTION_DEPOSIT_PLAYER2, 0);
+
+        let funded = Wager {
+            status: WagerStatus::WaitingPlayer1,
+            ..house
+        };
+        assert_ne!(funded.available_actions(0) & ACTION_DEPOSIT_PLAYER1, 0);
+    }
+
+    #[test]
+    fn the_vault_address_uses_the_stored_canonical_bump() {
+        let player1 = Pubkey::new_unique();
+        let pda_seed = Pubkey::new_unique();
+        let (vault, vault_bump) =
+            Pubkey::find_program_address(&[b"vault", player1.as_ref(), pda_seed.as_ref()], &crate::ID);
+        let wager = Wager {
+            player1,
+            pda_seed,
+            vault_bump,
+            ..Wager::default()
+        };
+        assert_eq!(wager.vault_address().unwrap(), vault);
+        // Any other bump reaches a different address, if any
+        let tampered = Wager {
+            vault_bump: vault_bump.wrapping_sub(1),
+            ..wager
+        };
+        assert_ne!(tampered.vault_address().ok(), Some(vault));
+    }
+
+    #[test]
+    fn frozen_settlements_leave_only_the_refund_and_voids() {
+        let active = Wager {
+            status: WagerStatus::Active,
+            start_time: 1_000,
+            ..Wager::default()
+        };
+        assert_ne!(active.available_actions(1_000) & ACTION_DECLARE_WINNER, 0);
+
+        let frozen = Wager {
+            settlements_frozen: true,
+            ..active
+        };
+        assert_eq!(frozen.available_actions(1_000) & ACTION_DECLARE_WINNER, 0);
+        assert_ne!(frozen.available_actions(1_000) & ACTION_ARBITER_VOID, 0);
+        let timed_out = 1_000 + TIMEOUT_SECONDS + 1;
+        assert_ne!(frozen.available_actions(timed_out) & ACTION_REFUND, 0);
+    }
+
+    #[test]
+    fn status_transitions_follow_the_lifecycle() {
+        use WagerStatus::*;
+        let all = [
+            Created,
+            WaitingPlayer2,
+            WaitingPlayer1,
+            Active,
+            Settled,
+            Refunded,
+            CancelledPlayer1Refunded,
+            CancelledPlayer2Refunded,
+            CancelledUnfunded,
+        ];
+        let legal = [
+            (Created, WaitingPlayer2),
+            (Created, WaitingPlayer1),
+            (Created, CancelledUnfunded),
+            (WaitingPlayer2, Active),
+            (WaitingPlayer2, CancelledPlayer1Refunded),
+            (WaitingPlayer1, Active),
+            (WaitingPlayer1, CancelledPlayer2Refunded),
+            (Active, Settled),
+            (Active, Refunded),
+            (CancelledPlayer1Refunded, Created),
+            (CancelledPlayer2Refunded, Created),
+        ];
+        for from in all {
+            for to in all {
+                assert_eq!(from.can_transition_to(to), legal.contains(&(from, to)), "{:?} -> {:?}", from, to);
+            }
+        }
+        // e.g. no refund of a game that never started, no second settlement
+        assert!(!WaitingPlayer2.can_transition_to(Refunded));
+        assert!(!Settled.can_transition_to(Settled));
+        assert!(!CancelledPlayer1Refunded.can_transition_to(Active));
+    }
+
+    #[test]
+    fn declared_id_round_trips_through_base58() {
+        let encoded = crate::ID.to_string();
+        assert_eq!(encoded, "9EeZ1eFrs8QAop7c6ihE4CiXenjVpGPdmFyv6w3XnmcT");
+        assert_eq!(encoded.parse::<Pubkey>().unwrap(), crate::ID);
+    }
+
+    #[test]
+    fn anchor_toml_deploys_the_declared_id_on_every_cluster() {
+        let anchor_toml = include_str!("../../../Anchor.toml");
+        let ids: Vec<&str> = anchor_toml
+            .lines()
+            .filter_map(|line| line.strip_prefix("slider_pvp = "))
+            .map(|value| value.trim_matches('"'))
+            .collect();
+        assert_eq!(ids.len(), 3);
+        for id in ids {
+            assert_eq!(id.parse::<Pubkey>().unwrap(), crate::ID);
+        }
+    }
+
+    #[test]
+    fn signed_result_message_layout() {
+        let wager = Pubkey::new_unique();
+        let message = signed_result_message(&wager, 2, 7);
+        assert_eq!(&message[..32], wager.as_ref());
+        assert_eq!(message[32], 2);
+        assert_eq!(message[33..], 7u64.to_le_bytes());
+    }
+
+    #[test]
+    fn parses_an_inline_ed25519_instruction() {
+        let arbiter = Pubkey::new_unique();
+        let message = signed_result_message(&Pubkey::new_unique(), 1, 0);
+        let data = ed25519_data(&arbiter, &message);
+        assert_eq!(parse_ed25519_instruction(&data), Some((arbiter, &message[..])));
+    }
+
+    #[test]
+    fn rejects_ed25519_data_from_other_instructions_or_truncated() {
+        let arbiter = Pubkey::new_unique();
+        let message = signed_result_message(&Pubkey::new_unique(), 1, 0);
+        let data = ed25519_data(&arbiter, &message);
+
+        // Message read from another instruction
+        let mut elsewhere = data.clone();
+        elsewhere[14..16].copy_from_slice(&0u16.to_le_bytes());
+        assert_eq!(parse_ed25519_instruction(&elsewhere), None);
+
+        // More than one signature
+        let mut two = data.clone();
+        two[0] = 2;
+        assert_eq!(parse_ed25519_instruction(&two), None);
+
+        assert_eq!(parse_ed25519_instruction(&data[..data.len() - 1]), None);
+        assert_eq!(parse_ed25519_instruction(&data[..15]), None);
+    }
+
+    #[test]
+    fn match_window_is_open_exactly_at_timeout() {
+        let start = 1_700_000_000;
+        assert_eq!(match_time_remaining(start, start, TIMEOUT_SECONDS), TIMEOUT_SECONDS);
+        assert_eq!(match_time_remaining(start, start + TIMEOUT_SECONDS, TIMEOUT_SECONDS), 0);
+    }
+
+    #[test]
+    fn match_window_expires_one_second_past_timeout() {
+        let start = 1_700_000_000;
+        assert_eq!(match_time_remaining(start, start + TIMEOUT_SECONDS + 1, TIMEOUT_SECONDS), -1);
+    }
+
+    #[test]
+    fn deposit_window_boundaries() {
+        let opened = 1_700_000_000;
+        assert_eq!(deposit_time_remaining(opened, opened + DEPOSIT_TIMEOUT_SECONDS), 0);
+        assert_eq!(deposit_time_remaining(opened, opened + DEPOSIT_TIMEOUT_SECONDS + 1), -1);
+    }
+
+    #[test]
+    fn wager_timeouts_expire_one_second_past_their_deadline() {
+        let start = 1_700_000_000;
+        let wager = Wager {
+            creation_time: start,
+            first_deposit_time: start + 10,
+            start_time: start + 20,
+            declared_time: start + 30,
+            claim_window_seconds: 60,
+            ..Wager::default()
+        };
+        // The deposit window runs from the first deposit
+        assert!(!wager.deposit_window_expired(start + 10 + DEPOSIT_TIMEOUT_SECONDS));
+        assert!(wager.deposit_window_expired(start + 10 + DEPOSIT_TIMEOUT_SECONDS + 1));
+        assert!(!wager.match_timed_out(start + 20 + TIMEOUT_SECONDS));
+        assert!(wager.match_timed_out(start + 20 + TIMEOUT_SECONDS + 1));
+        assert!(!wager.claim_window_expired(start + 30 + 60));
+        assert!(wager.claim_window_expired(start + 30 + 61));
+    }
+
+    #[test]
+    fn elapsed_rejects_a_clock_behind_start() {
+        let start = 1_700_000_000;
+        assert_eq!(match_elapsed(start, start - 1, 0), None);
+        assert_eq!(match_elapsed(start, start - 5, 5), Some(0));
+        assert_eq!(match_elapsed(start, start - 6, 5), None);
+        assert_eq!(match_elapsed(start, start + TIMEOUT_SECONDS, 0), Some(TIMEOUT_SECONDS));
+    }
+
+    #[test]
+    fn elapsed_saturates_instead_of_overflowing() {
+        assert_eq!(match_elapsed(i64::MIN, i64::MAX, 0), Some(i64::MAX));
+        assert_eq!(match_elapsed(i64::MAX, i64::MIN, i64::MAX), Some(0));
+    }
+
+    #[test]
+    fn remaining_time_saturates_instead_of_overflowing() {
+        assert_eq!(match_time_remaining(i64::MAX, 0, TIMEOUT_SECONDS), i64::MAX);
+        assert_eq!(match_time_remaining(0, i64::MIN, TIMEOUT_SECONDS), i64::MAX);
+    }
+}
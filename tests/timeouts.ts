@@ -0,0 +1,1387 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { SliderPvp } from "../target/types/slider_pvp";
+import { PublicKey, Keypair, LAMPORTS_PER_SOL, SystemProgram } from "@solana/web3.js";
+import { BankrunProvider, startAnchor } from "anchor-bankrun";
+import { Clock, ProgramTestContext } from "solana-bankrun";
+import { expect } from "chai";
+import { createHash } from "crypto";
+
+const IDL = require("../target/idl/slider_pvp.json");
+
+const TIMEOUT_SECONDS = 120;
+const DEPOSIT_TIMEOUT_SECONDS = 30;
+
+// Timeout scenarios run against an in-process bank (solana-bankrun) instead of the
+// test validator so the cluster clock can be set exactly, including on the boundaries.
+describe("slider-pvp timeouts", () => {
+  let context: ProgramTestContext;
+  let provider: BankrunProvider;
+  let program: Program<SliderPvp>;
+
+  let player1: Keypair;
+  let player2: Keypair;
+  let arbiter: Keypair;
+  let feeRecipient: Keypair;
+  let wagerPda: PublicKey;
+  let vaultPda: PublicKey;
+  let pairStatsPda: PublicKey;
+
+  const wagerAmount = new anchor.BN(0.1 * LAMPORTS_PER_SOL); // 0.1 SOL
+
+  beforeEach(async () => {
+    // Fresh bank per test so clock changes never leak between tests
+    context = await startAnchor("", [], []);
+    provider = new BankrunProvider(context);
+    program = new Program<SliderPvp>(IDL, provider);
+
+    player1 = Keypair.generate();
+    player2 = Keypair.generate();
+    arbiter = Keypair.generate();
+    feeRecipient = Keypair.generate();
+
+    fund(player1.publicKey, 1 * LAMPORTS_PER_SOL);
+    fund(player2.publicKey, 1 * LAMPORTS_PER_SOL);
+    fund(arbiter.publicKey, 1 * LAMPORTS_PER_SOL);
+
+    [wagerPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("wager"), player1.publicKey.toBuffer(), player2.publicKey.toBuffer()],
+      program.programId
+    );
+    [vaultPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), player1.publicKey.toBuffer(), player2.publicKey.toBuffer()],
+      program.programId
+    );
+    [pairStatsPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pair"), player1.publicKey.toBuffer(), player2.publicKey.toBuffer()],
+      program.programId
+    );
+  });
+
+  function fund(publicKey: PublicKey, lamports: number) {
+    context.setAccount(publicKey, {
+      lamports,
+      data: Buffer.alloc(0),
+      owner: SystemProgram.programId,
+      executable: false,
+    });
+  }
+
+  async function now(): Promise<number> {
+    const clock = await context.banksClient.getClock();
+    return Number(clock.unixTimestamp);
+  }
+
+  // Set the cluster clock to an exact unix timestamp. Also advance a slot so a retried
+  // instruction gets a fresh blockhash instead of being deduplicated.
+  async function warpTo(unixTimestamp: number) {
+    const clock = await context.banksClient.getClock();
+    const slot = clock.slot + BigInt(1);
+    context.warpToSlot(slot);
+    context.setClock(
+      new Clock(
+        slot,
+        clock.epochStartTimestamp,
+        clock.epoch,
+        clock.leaderScheduleEpoch,
+        BigInt(unixTimestamp)
+      )
+    );
+  }
+
+  async function getBalance(publicKey: PublicKey): Promise<number> {
+    return Number(await context.banksClient.getBalance(publicKey));
+  }
+
+  async function expectError(promise: Promise<unknown>, message: string) {
+    try {
+      await promise;
+      expect.fail("Expected error was not thrown");
+    } catch (error) {
+      const text = error.error?.errorMessage ?? `${error.message}\n${(error.logs ?? []).join("\n")}`;
+      expect(text).to.include(message);
+    }
+  }
+
+  const defaultOptions = {
+    payerDustBps: 0,
+    deductInitCostFromPool: true,
+    scheduledStart: new anchor.BN(0),
+    payYieldToWinner: false,
+    fastThresholdSeconds: new anchor.BN(0),
+    fastRebateBps: 0,
+    tournamentId: null as number[] | null,
+    arbiterRotation: [] as PublicKey[],
+    maxLifetimeSeconds: new anchor.BN(0),
+    feeSplit: null as { secondaryRecipient: PublicKey; primaryBps: number; secondaryBps: number } | null,
+    claimWindowSeconds: new anchor.BN(0),
+    minMatchSeconds: new anchor.BN(0),
+    feeWaiver: null as { matches: anchor.BN; waiverBps: number } | null,
+    gameRef: new Array(32).fill(0) as number[],
+    insurance: null as { pool: PublicKey; insuranceBps: number } | null,
+    startDelaySeconds: new anchor.BN(0),
+    escrowAuthority: null as PublicKey | null,
+    newPlayerWaiver: null as { matches: anchor.BN; waiverBps: number } | null,
+    arbiterFeeBps: 0,
+    roundFeeDown: true,
+    strictIsolation: false,
+    closeOnSettle: false,
+    keeperRewardBps: 10,
+    strictFeeRecipient: false,
+    feeVaultAuthority: null,
+    requireReady: false,
+    capActiveGames: false,
+  };
+
+  async function initializeWager(options: Partial<typeof defaultOptions> = {}) {
+    await program.methods
+      .initializeWager(
+        player1.publicKey,
+        player2.publicKey,
+        arbiter.publicKey,
+        feeRecipient.publicKey,
+        wagerAmount,
+        { ...defaultOptions, ...options }
+      )
+      .accounts({
+        wager: wagerPda,
+        vault: vaultPda,
+        pairStats: pairStatsPda,
+        payer: provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+  }
+
+  async function depositPlayer1() {
+    await program.methods
+      .depositPlayer1()
+      .accounts({
+        wager: wagerPda,
+        vault: vaultPda,
+        player1: player1.publicKey,
+        playerActivity: null,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([player1])
+      .rpc();
+  }
+
+  async function depositPlayer2() {
+    await program.methods
+      .depositPlayer2()
+      .accounts({
+        wager: wagerPda,
+        vault: vaultPda,
+        player2: player2.publicKey,
+        playerActivity: null,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([player2])
+      .rpc();
+  }
+
+  function declareWinner(winner: number) {
+    return program.methods
+      .declareWinner(winner)
+      .accounts({
+        wager: wagerPda,
+        vault: vaultPda,
+        arbiter: arbiter.publicKey,
+        winnerAccount: winner === 1 ? player1.publicKey : player2.publicKey,
+        feeRecipient: feeRecipient.publicKey,
+        secondaryFeeRecipient: null,
+        insurancePool: null,
+        escrowAuthority: null,
+        arbiterStats: null,
+        player1Stats: null,
+        player2Stats: null,
+        receipt: null,
+        receiptPayer: null,
+        payer: null,
+        pairStats: null,
+        player1: null,
+        player2: null,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([arbiter])
+      .rpc();
+  }
+
+  // `closeAccounts` passes what a close-on-settle wager needs to close itself
+  function refund(closeAccounts = false) {
+    return program.methods
+      .refund()
+      .accounts({
+        wager: wagerPda,
+        vault: vaultPda,
+        player1: player1.publicKey,
+        player2: player2.publicKey,
+        receipt: null,
+        receiptPayer: null,
+        payer: closeAccounts ? provider.wallet.publicKey : null,
+        pairStats: closeAccounts ? pairStatsPda : null,
+        feeRecipient: closeAccounts ? feeRecipient.publicKey : null,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+  }
+
+  // `closeAccounts` passes what a close-on-settle wager needs to close itself
+  function cancelWager(closeAccounts = false) {
+    return program.methods
+      .cancelWager()
+      .accounts({
+        wager: wagerPda,
+        vault: vaultPda,
+        player1: player1.publicKey,
+        player2: player2.publicKey,
+        receipt: null,
+        receiptPayer: null,
+        payer: closeAccounts ? provider.wallet.publicKey : null,
+        pairStats: closeAccounts ? pairStatsPda : null,
+        feeRecipient: closeAccounts ? feeRecipient.publicKey : null,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+  }
+
+  it("Declares a winner exactly at the match timeout", async () => {
+    await initializeWager();
+    await depositPlayer1();
+    await depositPlayer2();
+
+    const startTime = (await program.account.wager.fetch(wagerPda)).startTime.toNumber();
+    await warpTo(startTime + TIMEOUT_SECONDS);
+
+    await declareWinner(1);
+
+    const wagerAccount = await program.account.wager.fetch(wagerPda);
+    expect(wagerAccount.status).to.deep.equal({ settled: {} });
+    expect(wagerAccount.winner).to.equal(1);
+  });
+
+  it("Fails to declare a winner one second past the match timeout", async () => {
+    await initializeWager();
+    await depositPlayer1();
+    await depositPlayer2();
+
+    const startTime = (await program.account.wager.fetch(wagerPda)).startTime.toNumber();
+    await warpTo(startTime + TIMEOUT_SECONDS + 1);
+
+    await expectError(declareWinner(1), "Timeout period has expired");
+  });
+
+  it("Declares a winner past the match timeout when both players consent", async () => {
+    await initializeWager();
+    await depositPlayer1();
+    await depositPlayer2();
+
+    const startTime = (await program.account.wager.fetch(wagerPda)).startTime.toNumber();
+    await warpTo(startTime + TIMEOUT_SECONDS + 5);
+
+    // The plain declaration stays timeout-bound
+    await expectError(declareWinner(2), "Timeout period has expired");
+
+    await program.methods
+      .declareWinnerWithConsent(2)
+      .accounts({
+        wager: wagerPda,
+        vault: vaultPda,
+        arbiter: arbiter.publicKey,
+        player1: player1.publicKey,
+        player2: player2.publicKey,
+        winnerAccount: player2.publicKey,
+        feeRecipient: feeRecipient.publicKey,
+        secondaryFeeRecipient: null,
+        insurancePool: null,
+        escrowAuthority: null,
+        arbiterStats: null,
+        player1Stats: null,
+        player2Stats: null,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([arbiter, player1, player2])
+      .rpc();
+
+    const wagerAccount = await program.account.wager.fetch(wagerPda);
+    expect(wagerAccount.status).to.deep.equal({ settled: {} });
+    expect(wagerAccount.winner).to.equal(2);
+  });
+
+  it("Refunds both players after timeout", async () => {
+    await initializeWager();
+    await depositPlayer1();
+    await depositPlayer2();
+
+    let wagerAccount = await program.account.wager.fetch(wagerPda);
+    const startTime = wagerAccount.startTime.toNumber();
+    const initCost = wagerAccount.initializationCost.toNumber();
+
+    // Exactly at the timeout the arbiter still has the window, so no refund yet
+    await warpTo(startTime + TIMEOUT_SECONDS);
+    await expectError(refund(), "Timeout period has not expired");
+
+    const player1BalanceBefore = await getBalance(player1.publicKey);
+    const player2BalanceBefore = await getBalance(player2.publicKey);
+
+    await warpTo(startTime + TIMEOUT_SECONDS + 1);
+    await refund();
+
+    wagerAccount = await program.account.wager.fetch(wagerPda);
+    expect(wagerAccount.status).to.deep.equal({ refunded: {} });
+
+    const refundEach = Math.floor((wagerAmount.toNumber() * 2 - initCost) / 2);
+    expect((await getBalance(player1.publicKey)) - player1BalanceBefore).to.equal(refundEach);
+    expect((await getBalance(player2.publicKey)) - player2BalanceBefore).to.equal(refundEach);
+  });
+
+  function setSettlementsFrozen(frozen: boolean, authority: Keypair = arbiter) {
+    return program.methods
+      .setSettlementsFrozen(frozen)
+      .accounts({ wager: wagerPda, arbiter: authority.publicKey })
+      .signers([authority])
+      .rpc();
+  }
+
+  it("Blocks declarations while settlements are frozen but still refunds after timeout", async () => {
+    await initializeWager();
+    await depositPlayer1();
+    await depositPlayer2();
+
+    // Only the arbiter can freeze
+    await expectError(setSettlementsFrozen(true, player1), "Unauthorized arbiter");
+    await setSettlementsFrozen(true);
+    expect((await program.account.wager.fetch(wagerPda)).settlementsFrozen).to.be.true;
+
+    await expectError(declareWinner(1), "The arbiter has frozen settlements on this wager");
+
+    const player1BalanceBefore = await getBalance(player1.publicKey);
+    const startTime = (await program.account.wager.fetch(wagerPda)).startTime.toNumber();
+    await warpTo(startTime + TIMEOUT_SECONDS + 1);
+    await refund();
+
+    const wagerAccount = await program.account.wager.fetch(wagerPda);
+    expect(wagerAccount.status).to.deep.equal({ refunded: {} });
+    expect(await getBalance(player1.publicKey)).to.be.greaterThan(player1BalanceBefore);
+  });
+
+  it("Declares a winner again once settlements are unfrozen", async () => {
+    await initializeWager();
+    await depositPlayer1();
+    await depositPlayer2();
+
+    await setSettlementsFrozen(true);
+    await expectError(declareWinner(2), "The arbiter has frozen settlements on this wager");
+    await setSettlementsFrozen(false);
+    await declareWinner(2);
+
+    const wagerAccount = await program.account.wager.fetch(wagerPda);
+    expect(wagerAccount.status).to.deep.equal({ settled: {} });
+    expect(wagerAccount.winner).to.equal(2);
+  });
+
+  it("Recovers the stakes of a match the arbiter abandoned long ago", async () => {
+    await initializeWager();
+    await depositPlayer1();
+    await depositPlayer2();
+
+    const wagerAccount = await program.account.wager.fetch(wagerPda);
+    const startTime = wagerAccount.startTime.toNumber();
+    const initCost = wagerAccount.initializationCost.toNumber();
+
+    // Nobody acts for twice the timeout; a late declaration is still rejected
+    await warpTo(startTime + 2 * TIMEOUT_SECONDS + 1);
+    await expectError(declareWinner(1), "Timeout period has expired");
+
+    // A month later the stakes are still recoverable; refunds have no deadline
+    await warpTo(startTime + 30 * 24 * 60 * 60);
+    const player1BalanceBefore = await getBalance(player1.publicKey);
+    const player2BalanceBefore = await getBalance(player2.publicKey);
+
+    await refund();
+
+    // Both players get back their stake less their share of the initialization cost
+    const refundEach = Math.floor((wagerAmount.toNumber() * 2 - initCost) / 2);
+    expect((await getBalance(player1.publicKey)) - player1BalanceBefore).to.equal(refundEach);
+    expect((await getBalance(player2.publicKey)) - player2BalanceBefore).to.equal(refundEach);
+
+    const wagerAccountAfter = await program.account.wager.fetch(wagerPda);
+    expect(wagerAccountAfter.status).to.deep.equal({ refunded: {} });
+  });
+
+  it("Closes a close-on-settle wager within the refund", async () => {
+    await initializeWager({ closeOnSettle: true });
+    await depositPlayer1();
+    await depositPlayer2();
+
+    const startTime = (await program.account.wager.fetch(wagerPda)).startTime.toNumber();
+    await warpTo(startTime + TIMEOUT_SECONDS + 1);
+
+    // Without the accounts needed to close, the refund is rejected rather than left open
+    await expectError(refund(), "Closing on settlement requires");
+
+    const wagerRent = await getBalance(wagerPda);
+    const vaultRent = Number((await context.banksClient.getRent()).minimumBalance(BigInt(0)));
+    const payerBalanceBefore = await getBalance(provider.wallet.publicKey);
+    await refund(true);
+
+    // Both PDAs are gone and their rent is back with the payer (less the tx fee); the
+    // retained initialization cost goes to the fee recipient as dust
+    expect(await context.banksClient.getAccount(wagerPda)).to.be.null;
+    expect(await context.banksClient.getAccount(vaultPda)).to.be.null;
+    expect((await getBalance(provider.wallet.publicKey)) - payerBalanceBefore).to.equal(wagerRent + vaultRent - 5000);
+    const pairStats = await program.account.pairStats.fetch(pairStatsPda);
+    expect(pairStats.settlementCount.toNumber()).to.equal(1);
+  });
+
+  it("Refunds each player's full stake when the initialization cost is not deducted", async () => {
+    await initializeWager({ deductInitCostFromPool: false });
+    await depositPlayer1();
+    await depositPlayer2();
+
+    const startTime = (await program.account.wager.fetch(wagerPda)).startTime.toNumber();
+    const player1BalanceBefore = await getBalance(player1.publicKey);
+    const player2BalanceBefore = await getBalance(player2.publicKey);
+
+    await warpTo(startTime + TIMEOUT_SECONDS + 1);
+    await refund();
+
+    // No-fault refund: the payer keeps bearing the initialization cost
+    expect((await getBalance(player1.publicKey)) - player1BalanceBefore).to.equal(wagerAmount.toNumber());
+    expect((await getBalance(player2.publicKey)) - player2BalanceBefore).to.equal(wagerAmount.toNumber());
+  });
+
+  // `closeAccounts` passes what a close-on-settle wager needs to close itself
+  function crankRefund(keeper: Keypair, closeAccounts = false) {
+    return program.methods
+      .crankRefund()
+      .accounts({
+        wager: wagerPda,
+        vault: vaultPda,
+        player1: player1.publicKey,
+        player2: player2.publicKey,
+        keeper: keeper.publicKey,
+        receipt: null,
+        receiptPayer: null,
+        payer: closeAccounts ? provider.wallet.publicKey : null,
+        pairStats: closeAccounts ? pairStatsPda : null,
+        feeRecipient: closeAccounts ? feeRecipient.publicKey : null,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([keeper])
+      .rpc();
+  }
+
+  it("Lets a third party crank the refund of an expired match for a keeper tip", async () => {
+    const KEEPER_TIP_LAMPORTS = 5000;
+    const keeper = Keypair.generate();
+    fund(keeper.publicKey, LAMPORTS_PER_SOL);
+
+    await initializeWager({ keeperRewardBps: 0 });
+    await depositPlayer1();
+    await depositPlayer2();
+    const wagerAccount = await program.account.wager.fetch(wagerPda);
+    const startTime = wagerAccount.startTime.toNumber();
+    const initCost = wagerAccount.initializationCost.toNumber();
+
+    await warpTo(startTime + TIMEOUT_SECONDS);
+    await expectError(crankRefund(keeper), "Timeout period has not expired");
+
+    const player1BalanceBefore = await getBalance(player1.publicKey);
+    const player2BalanceBefore = await getBalance(player2.publicKey);
+    const keeperBalanceBefore = await getBalance(keeper.publicKey);
+
+    await warpTo(startTime + TIMEOUT_SECONDS + 1);
+    await crankRefund(keeper);
+
+    // Without a keeper reward each player only pays half the tip out of their refund;
+    // the dust stays in the vault for the payer and fee recipient
+    const refundEach = Math.floor((wagerAmount.toNumber() * 2 - initCost) / 2);
+    const tipEach = KEEPER_TIP_LAMPORTS / 2;
+    expect((await getBalance(player1.publicKey)) - player1BalanceBefore).to.equal(refundEach - tipEach);
+    expect((await getBalance(player2.publicKey)) - player2BalanceBefore).to.equal(refundEach - tipEach);
+    expect((await getBalance(keeper.publicKey)) - keeperBalanceBefore).to.equal(KEEPER_TIP_LAMPORTS);
+    expect((await program.account.wager.fetch(wagerPda)).status).to.deep.equal({ refunded: {} });
+  });
+
+  it("Pays the keeper the configured reward out of the refunded pool", async () => {
+    const KEEPER_TIP_LAMPORTS = 5000;
+    const keeper = Keypair.generate();
+    fund(keeper.publicKey, LAMPORTS_PER_SOL);
+
+    // The reward is capped at 100 bps
+    await expectError(initializeWager({ keeperRewardBps: 101 }), "Keeper reward cannot exceed");
+
+    await initializeWager({ keeperRewardBps: 50 });
+    await depositPlayer1();
+    await depositPlayer2();
+    const wagerAccount = await program.account.wager.fetch(wagerPda);
+    expect(wagerAccount.keeperRewardBps).to.equal(50);
+
+    const player1BalanceBefore = await getBalance(player1.publicKey);
+    const player2BalanceBefore = await getBalance(player2.publicKey);
+    const keeperBalanceBefore = await getBalance(keeper.publicKey);
+
+    await warpTo(wagerAccount.startTime.toNumber() + TIMEOUT_SECONDS + 1);
+    await crankRefund(keeper);
+
+    // Each player gives up 0.5% of their refund and half the tip to the keeper
+    const refundEach = Math.floor((wagerAmount.toNumber() * 2 - wagerAccount.initializationCost.toNumber()) / 2);
+    const rewardEach = Math.floor((refundEach * 50) / 10000);
+    const tipEach = KEEPER_TIP_LAMPORTS / 2;
+    expect((await getBalance(player1.publicKey)) - player1BalanceBefore).to.equal(refundEach - rewardEach - tipEach);
+    expect((await getBalance(player2.publicKey)) - player2BalanceBefore).to.equal(refundEach - rewardEach - tipEach);
+    expect((await getBalance(keeper.publicKey)) - keeperBalanceBefore).to.equal(2 * rewardEach + KEEPER_TIP_LAMPORTS);
+  });
+
+  it("Closes a close-on-settle wager within the crank", async () => {
+    const keeper = Keypair.generate();
+    fund(keeper.publicKey, LAMPORTS_PER_SOL);
+
+    await initializeWager({ closeOnSettle: true });
+    await depositPlayer1();
+    await depositPlayer2();
+
+    const startTime = (await program.account.wager.fetch(wagerPda)).startTime.toNumber();
+    await warpTo(startTime + TIMEOUT_SECONDS + 1);
+
+    // As with `refund`, a crank that cannot close the wager is rejected rather than left open
+    await expectError(crankRefund(keeper), "Closing on settlement requires");
+
+    const keeperBalanceBefore = await getBalance(keeper.publicKey);
+    await crankRefund(keeper, true);
+
+    expect(await context.banksClient.getAccount(wagerPda)).to.be.null;
+    expect(await context.banksClient.getAccount(vaultPda)).to.be.null;
+    expect(await getBalance(keeper.publicKey)).to.be.greaterThan(keeperBalanceBefore);
+  });
+
+  it("Voids a wager once it has expired, whatever its deposit state", async () => {
+    const WAGER_EXPIRY_SECONDS = 60 * 60;
+    function voidExpired() {
+      return program.methods
+        .voidExpired()
+        .accounts({
+          wager: wagerPda,
+          vault: vaultPda,
+          player1: player1.publicKey,
+          player2: player2.publicKey,
+          receipt: null,
+          receiptPayer: null,
+          payer: null,
+          pairStats: null,
+          feeRecipient: null,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+    }
+
+    await initializeWager();
+    let wagerAccount = await program.account.wager.fetch(wagerPda);
+    const creationTime = wagerAccount.creationTime.toNumber();
+    expect(wagerAccount.expiryTime.toNumber()).to.equal(creationTime + WAGER_EXPIRY_SECONDS);
+
+    // Only player 1 ever deposits
+    await depositPlayer1();
+    const initCost = (await program.account.wager.fetch(wagerPda)).initializationCost.toNumber();
+
+    await warpTo(creationTime + WAGER_EXPIRY_SECONDS);
+    await expectError(voidExpired(), "Wager has not expired yet");
+
+    const player1BalanceBefore = await getBalance(player1.publicKey);
+    const player2BalanceBefore = await getBalance(player2.publicKey);
+
+    await warpTo(creationTime + WAGER_EXPIRY_SECONDS + 1);
+    await voidExpired();
+
+    wagerAccount = await program.account.wager.fetch(wagerPda);
+    expect(wagerAccount.winner).to.be.null;
+    expect(wagerAccount.status).to.deep.equal({ cancelledPlayer1Refunded: {} });
+    expect((await getBalance(player1.publicKey)) - player1BalanceBefore).to.equal(
+      wagerAmount.toNumber() - initCost
+    );
+    expect(await getBalance(player2.publicKey)).to.equal(player2BalanceBefore);
+
+    await expectError(voidExpired(), "Wager has already been settled");
+  });
+
+  it("Cancels wager when only player 1 deposited", async () => {
+    await initializeWager();
+    await depositPlayer1();
+
+    let wagerAccount = await program.account.wager.fetch(wagerPda);
+    const firstDepositTime = wagerAccount.firstDepositTime.toNumber();
+    const initCost = wagerAccount.initializationCost.toNumber();
+
+    await warpTo(firstDepositTime + DEPOSIT_TIMEOUT_SECONDS);
+    await expectError(cancelWager(), "Deposit timeout has not expired yet");
+
+    const player1BalanceBefore = await getBalance(player1.publicKey);
+
+    await warpTo(firstDepositTime + DEPOSIT_TIMEOUT_SECONDS + 1);
+    await cancelWager();
+
+    wagerAccount = await program.account.wager.fetch(wagerPda);
+    expect(wagerAccount.status).to.deep.equal({ cancelledPlayer1Refunded: {} });
+    expect((await getBalance(player1.publicKey)) - player1BalanceBefore).to.equal(
+      wagerAmount.toNumber() - initCost
+    );
+  });
+
+  it("Fails to cancel cleanly when the vault cannot cover the refund", async () => {
+    await initializeWager();
+    await depositPlayer1();
+
+    const firstDepositTime = (await program.account.wager.fetch(wagerPda)).firstDepositTime.toNumber();
+
+    // Drain the vault down to its rent reserve plus a few lamports
+    const vault = await context.banksClient.getAccount(vaultPda);
+    const rent = await context.banksClient.getRent();
+    const vaultRent = Number(rent.minimumBalance(BigInt(0)));
+    context.setAccount(vaultPda, { ...vault, lamports: vaultRent + 1000 });
+
+    await warpTo(firstDepositTime + DEPOSIT_TIMEOUT_SECONDS + 1);
+    await expectError(cancelWager(), "Vault holds less than the amount being paid out");
+
+    const wagerAccount = await program.account.wager.fetch(wagerPda);
+    expect(wagerAccount.status).to.deep.equal({ waitingPlayer2: {} });
+  });
+
+  it("Closes a close-on-settle wager within the cancellation", async () => {
+    await initializeWager({ closeOnSettle: true });
+    await depositPlayer1();
+
+    const firstDepositTime = (await program.account.wager.fetch(wagerPda)).firstDepositTime.toNumber();
+    await warpTo(firstDepositTime + DEPOSIT_TIMEOUT_SECONDS + 1);
+
+    const player1BalanceBefore = await getBalance(player1.publicKey);
+    await cancelWager(true);
+
+    expect(await context.banksClient.getAccount(wagerPda)).to.be.null;
+    expect(await context.banksClient.getAccount(vaultPda)).to.be.null;
+    expect(await getBalance(player1.publicKey)).to.be.greaterThan(player1BalanceBefore);
+  });
+
+  it("Cancels wager when only player 2 deposited", async () => {
+    await initializeWager();
+    await depositPlayer2();
+
+    let wagerAccount = await program.account.wager.fetch(wagerPda);
+    const firstDepositTime = wagerAccount.firstDepositTime.toNumber();
+    const initCost = wagerAccount.initializationCost.toNumber();
+
+    const player2BalanceBefore = await getBalance(player2.publicKey);
+
+    await warpTo(firstDepositTime + DEPOSIT_TIMEOUT_SECONDS + 1);
+    await cancelWager();
+
+    wagerAccount = await program.account.wager.fetch(wagerPda);
+    expect(wagerAccount.status).to.deep.equal({ cancelledPlayer2Refunded: {} });
+    expect((await getBalance(player2.publicKey)) - player2BalanceBefore).to.equal(
+      wagerAmount.toNumber() - initCost
+    );
+  });
+
+  it("Refunds player 1 of an open challenge nobody joined", async () => {
+    const nonce = new anchor.BN(1);
+    const seed = new PublicKey(
+      createHash("sha256")
+        .update(Buffer.concat([Buffer.from("open_wager"), nonce.toArrayLike(Buffer, "le", 8)]))
+        .digest()
+    );
+    [wagerPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("wager"), player1.publicKey.toBuffer(), seed.toBuffer()],
+      program.programId
+    );
+    [vaultPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), player1.publicKey.toBuffer(), seed.toBuffer()],
+      program.programId
+    );
+    [pairStatsPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pair"), player1.publicKey.toBuffer(), seed.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .initializeOpenWager(player1.publicKey, nonce, arbiter.publicKey, feeRecipient.publicKey, wagerAmount, defaultOptions)
+      .accounts({
+        wager: wagerPda,
+        vault: vaultPda,
+        pairStats: pairStatsPda,
+        payer: provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+    await depositPlayer1();
+
+    let wagerAccount = await program.account.wager.fetch(wagerPda);
+    const firstDepositTime = wagerAccount.firstDepositTime.toNumber();
+    const initCost = wagerAccount.initializationCost.toNumber();
+    const player1BalanceBefore = await getBalance(player1.publicKey);
+
+    // Player 2 is still unset, so there is no player 2 account to pass
+    await warpTo(firstDepositTime + DEPOSIT_TIMEOUT_SECONDS + 1);
+    await program.methods
+      .cancelWager()
+      .accounts({
+        wager: wagerPda,
+        vault: vaultPda,
+        player1: player1.publicKey,
+        player2: null,
+        receipt: null,
+        receiptPayer: null,
+        payer: null,
+        pairStats: null,
+        feeRecipient: null,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    wagerAccount = await program.account.wager.fetch(wagerPda);
+    expect(wagerAccount.status).to.deep.equal({ cancelledPlayer1Refunded: {} });
+    expect((await getBalance(player1.publicKey)) - player1BalanceBefore).to.equal(
+      wagerAmount.toNumber() - initCost
+    );
+  });
+
+  it("Fails to cancel a wager once a winner has been declared", async () => {
+    await initializeWager();
+    await depositPlayer1();
+    await depositPlayer2();
+    await declareWinner(1);
+
+    const wagerAccount = await program.account.wager.fetch(wagerPda);
+    await warpTo(wagerAccount.firstDepositTime.toNumber() + DEPOSIT_TIMEOUT_SECONDS + 1);
+    await expectError(cancelWager(), "Wager has already been settled");
+
+    expect((await program.account.wager.fetch(wagerPda)).status).to.deep.equal({ settled: {} });
+  });
+
+  it("Fails to declare a winner once the wager has been cancelled", async () => {
+    await initializeWager();
+    await depositPlayer1();
+
+    const firstDepositTime = (await program.account.wager.fetch(wagerPda)).firstDepositTime.toNumber();
+    await warpTo(firstDepositTime + DEPOSIT_TIMEOUT_SECONDS + 1);
+    await cancelWager();
+
+    // Neither the missing deposit nor a declaration can follow the cancellation
+    await expectError(depositPlayer2(), "Wager has already been settled");
+    await expectError(declareWinner(1), "Wager has already been settled");
+
+    const wagerAccount = await program.account.wager.fetch(wagerPda);
+    expect(wagerAccount.status).to.deep.equal({ cancelledPlayer1Refunded: {} });
+    expect(wagerAccount.winner).to.be.null;
+  });
+
+  function reopenWager(player: Keypair) {
+    return program.methods
+      .reopenWager()
+      .accounts({
+        wager: wagerPda,
+        vault: vaultPda,
+        player: player.publicKey,
+      })
+      .signers([player])
+      .rpc();
+  }
+
+  it("Reopens a cancelled wager when the missing player returns, and plays it out", async () => {
+    await initializeWager();
+    await depositPlayer1();
+    const initCost = (await program.account.wager.fetch(wagerPda)).initializationCost.toNumber();
+
+    const firstDepositTime = (await program.account.wager.fetch(wagerPda)).firstDepositTime.toNumber();
+    await warpTo(firstDepositTime + DEPOSIT_TIMEOUT_SECONDS + 1);
+    await cancelWager();
+
+    // Only the player who never deposited can bring the wager back
+    await expectError(reopenWager(player1), "Unauthorized player");
+    await reopenWager(player2);
+
+    let wagerAccount = await program.account.wager.fetch(wagerPda);
+    expect(wagerAccount.status).to.deep.equal({ created: {} });
+    expect(wagerAccount.startTime.toNumber()).to.equal(0);
+    // The initialization cost was already retained by the cancellation
+    expect(wagerAccount.initializationCost.toNumber()).to.equal(0);
+    expect(wagerAccount.vaultPrefund.toNumber()).to.equal(initCost);
+
+    // The refunded player re-deposits and the match runs as usual
+    await depositPlayer1();
+    await depositPlayer2();
+    wagerAccount = await program.account.wager.fetch(wagerPda);
+    expect(wagerAccount.status).to.deep.equal({ active: {} });
+
+    const player2BalanceBefore = await getBalance(player2.publicKey);
+    await declareWinner(2);
+
+    const pool = 2 * wagerAmount.toNumber();
+    const winnerAmount = pool - Math.floor((pool * 5) / 100);
+    expect((await getBalance(player2.publicKey)) - player2BalanceBefore).to.equal(winnerAmount);
+    expect((await program.account.wager.fetch(wagerPda)).winner).to.equal(2);
+  });
+
+  it("Fails to reopen a cancelled wager after the reopen window", async () => {
+    const REOPEN_WINDOW_SECONDS = 5 * 60;
+    await initializeWager();
+    await depositPlayer1();
+
+    const firstDepositTime = (await program.account.wager.fetch(wagerPda)).firstDepositTime.toNumber();
+    const cancelTime = firstDepositTime + DEPOSIT_TIMEOUT_SECONDS + 1;
+    await warpTo(cancelTime);
+    await cancelWager();
+    expect((await program.account.wager.fetch(wagerPda)).cancelledTime.toNumber()).to.equal(cancelTime);
+
+    await warpTo(cancelTime + REOPEN_WINDOW_SECONDS + 1);
+    await expectError(reopenWager(player2), "The window to reopen this cancelled wager has passed");
+    expect((await program.account.wager.fetch(wagerPda)).status).to.deep.equal({ cancelledPlayer1Refunded: {} });
+  });
+
+  function readyUp(authority: Keypair) {
+    return program.methods
+      .readyUp()
+      .accounts({ wager: wagerPda, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+  }
+
+  it("Starts the match timer only once both players have readied up", async () => {
+    await initializeWager({ requireReady: true });
+    await depositPlayer1();
+    await depositPlayer2();
+
+    let wagerAccount = await program.account.wager.fetch(wagerPda);
+    expect(wagerAccount.status).to.deep.equal({ active: {} });
+    expect(wagerAccount.startTime.toNumber()).to.equal(0);
+    const depositsCompleted = wagerAccount.depositsCompletedTime.toNumber();
+
+    // No winner can be declared before the handshake completes
+    await expectError(declareWinner(1), "Match has not started yet");
+    // and only the players can ready up within the ready timeout
+    await expectError(readyUp(arbiter), "Unauthorized player");
+
+    await warpTo(depositsCompleted + 5);
+    await readyUp(player1);
+    wagerAccount = await program.account.wager.fetch(wagerPda);
+    expect(wagerAccount.player1Ready).to.be.true;
+    expect(wagerAccount.startTime.toNumber()).to.equal(0);
+
+    await warpTo(depositsCompleted + 10);
+    await readyUp(player2);
+    wagerAccount = await program.account.wager.fetch(wagerPda);
+    expect(wagerAccount.player2Ready).to.be.true;
+    // The timer runs from the handshake, not from the deposits
+    expect(wagerAccount.startTime.toNumber()).to.equal(depositsCompleted + 10);
+
+    await expectError(readyUp(player1), "Match has already started");
+    await declareWinner(1);
+    expect((await program.account.wager.fetch(wagerPda)).winner).to.equal(1);
+  });
+
+  it("Tells a missing deposit apart from a match still waiting for the ready handshake", async () => {
+    await initializeWager({ requireReady: true });
+    await depositPlayer1();
+    await expectError(declareWinner(1), "Both players must deposit before declaring winner");
+
+    // Both deposited, but only one player is ready: the match has not started
+    await depositPlayer2();
+    await readyUp(player1);
+    await expectError(declareWinner(1), "Match has not started yet");
+    expect((await program.account.wager.fetch(wagerPda)).status).to.deep.equal({ active: {} });
+  });
+
+  it("Readies both players automatically once the ready timeout passes", async () => {
+    const READY_TIMEOUT_SECONDS = 30;
+    await initializeWager({ requireReady: true });
+    await depositPlayer1();
+    await depositPlayer2();
+    const depositsCompleted = (await program.account.wager.fetch(wagerPda)).depositsCompletedTime.toNumber();
+
+    // Player 2 never readies up
+    await readyUp(player1);
+    await warpTo(depositsCompleted + READY_TIMEOUT_SECONDS);
+    await expectError(readyUp(arbiter), "Unauthorized player");
+
+    // Past the timeout anyone may start the match
+    await warpTo(depositsCompleted + READY_TIMEOUT_SECONDS + 1);
+    await readyUp(arbiter);
+    const wagerAccount = await program.account.wager.fetch(wagerPda);
+    expect(wagerAccount.player1Ready).to.be.true;
+    expect(wagerAccount.player2Ready).to.be.true;
+    expect(wagerAccount.startTime.toNumber()).to.equal(depositsCompleted + READY_TIMEOUT_SECONDS + 1);
+  });
+
+  it("Fails to ready up on a wager without the ready handshake", async () => {
+    await initializeWager();
+    await depositPlayer1();
+    await depositPlayer2();
+
+    await expectError(readyUp(player1), "Wager does not use the ready handshake");
+  });
+
+  it("Cancels wager when neither player deposited", async () => {
+    await initializeWager();
+
+    const creationTime = (await program.account.wager.fetch(wagerPda)).creationTime.toNumber();
+
+    await warpTo(creationTime + DEPOSIT_TIMEOUT_SECONDS);
+    await expectError(cancelWager(), "Deposit timeout has not expired yet");
+
+    await warpTo(creationTime + DEPOSIT_TIMEOUT_SECONDS + 1);
+    await cancelWager();
+
+    const wagerAccount = await program.account.wager.fetch(wagerPda);
+    expect(wagerAccount.status).to.deep.equal({ cancelledUnfunded: {} });
+  });
+
+  it("Rejects re-initializing a pair within its cooldown", async () => {
+    const COOLDOWN_SECONDS = 60;
+    await initializeWager();
+    const creationTime = (await program.account.wager.fetch(wagerPda)).creationTime.toNumber();
+
+    await program.methods
+      .setPairCooldown(new anchor.BN(COOLDOWN_SECONDS))
+      .accounts({ pairStats: pairStatsPda, player1: player1.publicKey, player2: player2.publicKey })
+      .signers([player1, player2])
+      .rpc();
+
+    // Cancel and close the unfunded wager, then try to create it again straight away
+    await warpTo(creationTime + DEPOSIT_TIMEOUT_SECONDS + 1);
+    await cancelWager();
+    await program.methods
+      .closeWager()
+      .accounts({
+        wager: wagerPda,
+        vault: vaultPda,
+        pairStats: pairStatsPda,
+        payer: provider.wallet.publicKey,
+        feeRecipient: feeRecipient.publicKey,
+      })
+      .rpc();
+    await expectError(initializeWager(), "still in its initialization cooldown");
+
+    await warpTo(creationTime + COOLDOWN_SECONDS);
+    await initializeWager();
+
+    const pairStats = await program.account.pairStats.fetch(pairStatsPda);
+    expect(pairStats.initializationCount.toNumber()).to.equal(2);
+    expect(pairStats.lastInitTime.toNumber()).to.equal(creationTime + COOLDOWN_SECONDS);
+  });
+
+  it("Gives the second player a full deposit window after a late first deposit", async () => {
+    await initializeWager();
+    const creationTime = (await program.account.wager.fetch(wagerPda)).creationTime.toNumber();
+
+    // Player 1 deposits one second before the creation-based deadline
+    await warpTo(creationTime + DEPOSIT_TIMEOUT_SECONDS - 1);
+    await depositPlayer1();
+
+    // Past the creation-based deadline, but the window now runs from the first deposit
+    await warpTo(creationTime + DEPOSIT_TIMEOUT_SECONDS + 1);
+    await expectError(cancelWager(), "Deposit timeout has not expired yet");
+
+    // And player 2 can still join
+    await depositPlayer2();
+
+    const wagerAccount = await program.account.wager.fetch(wagerPda);
+    expect(wagerAccount.status).to.deep.equal({ active: {} });
+    expect(wagerAccount.startTime.toNumber()).to.equal(creationTime + DEPOSIT_TIMEOUT_SECONDS + 1);
+  });
+
+  it("Starts the match timer at the scheduled start and enforces the timeout from there", async () => {
+    const scheduledStart = (await now()) + 600;
+    await initializeWager({ scheduledStart: new anchor.BN(scheduledStart) });
+    await depositPlayer1();
+    await depositPlayer2();
+
+    // Too early to decide a scheduled match
+    await expectError(declareWinner(2), "Match has not started yet");
+
+    await warpTo(scheduledStart + TIMEOUT_SECONDS + 1);
+    await expectError(declareWinner(2), "Timeout period has expired");
+    await refund();
+
+    const wagerAccount = await program.account.wager.fetch(wagerPda);
+    expect(wagerAccount.status).to.deep.equal({ refunded: {} });
+    expect(wagerAccount.winner).to.be.null;
+  });
+
+  it("Starts the match timer after the start delay and measures the timeout from there", async () => {
+    const startDelay = 30;
+    await initializeWager({ startDelaySeconds: new anchor.BN(startDelay) });
+    await depositPlayer1();
+    await depositPlayer2();
+    const secondDeposit = await now();
+
+    const wagerAccount = await program.account.wager.fetch(wagerPda);
+    expect(wagerAccount.startDelaySeconds.toNumber()).to.equal(startDelay);
+    expect(wagerAccount.startTime.toNumber()).to.equal(secondDeposit + startDelay);
+
+    // The heads-up period does not count as match time
+    await expectError(declareWinner(1), "Match has not started yet");
+
+    // A refund only opens once the timeout has run from the delayed start
+    await warpTo(secondDeposit + TIMEOUT_SECONDS + 1);
+    await expectError(refund(), "Timeout period has not expired yet");
+    await declareWinner(1);
+
+    const settled = await program.account.wager.fetch(wagerPda);
+    expect(settled.winner).to.equal(1);
+  });
+
+  it("Rejects a start delay above the maximum", async () => {
+    await expectError(
+      initializeWager({ startDelaySeconds: new anchor.BN(61) }),
+      "Start delay must be between 0 and 60 seconds"
+    );
+  });
+
+  it("Fails to settle when the vault holds less than the expected pool", async () => {
+    await initializeWager();
+    await depositPlayer1();
+    await depositPlayer2();
+
+    // Overwrite the vault with only one player's stake on top of its rent reserve
+    const vault = await context.banksClient.getAccount(vaultPda);
+    const rent = await context.banksClient.getRent();
+    const vaultRent = Number(rent.minimumBalance(BigInt(0)));
+    context.setAccount(vaultPda, { ...vault, lamports: vaultRent + wagerAmount.toNumber() });
+
+    await expectError(declareWinner(1), "Vault balance is below the expected pool");
+  });
+
+  it("Reclaims an abandoned wager's rent for the payer once the deposit timeout passes", async () => {
+    await initializeWager();
+
+    const creationTime = (await program.account.wager.fetch(wagerPda)).creationTime.toNumber();
+    const reclaim = () =>
+      program.methods
+        .reclaimAbandoned()
+        .accounts({ wager: wagerPda, vault: vaultPda, payer: provider.wallet.publicKey })
+        .rpc();
+
+    await warpTo(creationTime + DEPOSIT_TIMEOUT_SECONDS);
+    await expectError(reclaim(), "Deposit timeout has not expired yet");
+
+    const wagerRent = await getBalance(wagerPda);
+    const vaultRent = await getBalance(vaultPda);
+    const payerBalanceBefore = await getBalance(provider.wallet.publicKey);
+
+    await warpTo(creationTime + DEPOSIT_TIMEOUT_SECONDS + 1);
+    await reclaim();
+
+    // Both PDAs are closed and their rent is back with the payer (less the tx fee)
+    expect(await context.banksClient.getAccount(wagerPda)).to.be.null;
+    expect(await context.banksClient.getAccount(vaultPda)).to.be.null;
+    const payerBalanceAfter = await getBalance(provider.wallet.publicKey);
+    expect(payerBalanceAfter - payerBalanceBefore).to.equal(wagerRent + vaultRent - 5000);
+  });
+
+  it("Lets the payer reclaim the full rent of a wager cancelled without deposits", async () => {
+    await initializeWager();
+
+    const creationTime = (await program.account.wager.fetch(wagerPda)).creationTime.toNumber();
+    await warpTo(creationTime + DEPOSIT_TIMEOUT_SECONDS + 1);
+    await cancelWager();
+
+    const reclaimInitCost = (payer: Keypair | null) => {
+      const builder = program.methods.reclaimInitCost().accounts({
+        wager: wagerPda,
+        vault: vaultPda,
+        payer: payer ? payer.publicKey : provider.wallet.publicKey,
+      });
+      return payer ? builder.signers([payer]).rpc() : builder.rpc();
+    };
+
+    // Only the payer that fronted the rent can reclaim it
+    await expectError(reclaimInitCost(player1), "original payer");
+
+    const wagerRent = await getBalance(wagerPda);
+    const vaultRent = await getBalance(vaultPda);
+    const payerBalanceBefore = await getBalance(provider.wallet.publicKey);
+
+    await reclaimInitCost(null);
+
+    // Both PDAs are closed and all their rent is back with the payer (less the tx fee)
+    expect(await context.banksClient.getAccount(wagerPda)).to.be.null;
+    expect(await context.banksClient.getAccount(vaultPda)).to.be.null;
+    expect((await getBalance(provider.wallet.publicKey)) - payerBalanceBefore).to.equal(
+      wagerRent + vaultRent - 5000
+    );
+  });
+
+  it("Neither settles nor refunds when the clock reads behind the match start", async () => {
+    await initializeWager();
+    await depositPlayer1();
+    await depositPlayer2();
+
+    const startTime = (await program.account.wager.fetch(wagerPda)).startTime.toNumber();
+
+    // A skewed clock must not let a negative elapsed time pass the timeout check
+    await warpTo(startTime - 1);
+    await expectError(declareWinner(1), "Match has not started yet");
+    // Nor make a refund look due
+    await expectError(refund(), "Timeout period has not expired");
+
+    // Once the clock catches up the arbiter can settle normally
+    await warpTo(startTime);
+    await declareWinner(1);
+    expect((await program.account.wager.fetch(wagerPda)).winner).to.equal(1);
+  });
+
+  it("Rejects an instant declaration before the minimum match duration", async () => {
+    const minMatchSeconds = 20;
+    await initializeWager({ minMatchSeconds: new anchor.BN(minMatchSeconds) });
+    await depositPlayer1();
+    await depositPlayer2();
+
+    const startTime = (await program.account.wager.fetch(wagerPda)).startTime.toNumber();
+    await expectError(declareWinner(1), "Match has not lasted the minimum duration");
+
+    await warpTo(startTime + minMatchSeconds - 1);
+    await expectError(declareWinner(1), "Match has not lasted the minimum duration");
+
+    await warpTo(startTime + minMatchSeconds);
+    await declareWinner(1);
+
+    const wagerAccount = await program.account.wager.fetch(wagerPda);
+    expect(wagerAccount.status).to.deep.equal({ settled: {} });
+    expect(wagerAccount.winner).to.equal(1);
+  });
+
+  // Rewrite the stored vault bump to a different value, as if the wager account were tampered with
+  async function corruptVaultBump() {
+    const account = await context.banksClient.getAccount(wagerPda);
+    const wager = program.coder.accounts.decode("wager", Buffer.from(account.data));
+    wager.vaultBump = (wager.vaultBump + 255) % 256;
+    const data = await program.coder.accounts.encode("wager", wager);
+    context.setAccount(wagerPda, { ...account, data });
+  }
+
+  it("Rejects deposits cleanly when the stored vault bump is corrupted", async () => {
+    await initializeWager();
+    await corruptVaultBump();
+
+    await expectError(depositPlayer1(), "seeds constraint was violated");
+  });
+
+  it("Rejects settlement and refund cleanly when the stored vault bump is corrupted", async () => {
+    await initializeWager();
+    await depositPlayer1();
+    await depositPlayer2();
+    const vaultBalance = await getBalance(vaultPda);
+    await corruptVaultBump();
+
+    await expectError(declareWinner(1), "seeds constraint was violated");
+    const startTime = (await program.account.wager.fetch(wagerPda)).startTime.toNumber();
+    await warpTo(startTime + TIMEOUT_SECONDS + 1);
+    await expectError(refund(), "seeds constraint was violated");
+
+    // Nothing left the vault
+    expect(await getBalance(vaultPda)).to.equal(vaultBalance);
+  });
+
+  function migrateWager() {
+    return program.methods
+      .migrateWager()
+      .accounts({
+        wager: wagerPda,
+        payer: provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+  }
+
+  it("Migrates a v1 wager account to the current layout", async () => {
+    await initializeWager();
+    await depositPlayer1();
+
+    // Rewrite the account as v1 did: same fields without the trailing fields v2 appended
+    // (`version`, `hasSidePool`, `status`, `newPlayerWaiver`, `arbiterFeeBps`,
+    // `roundFeeDown`, `expiryTime`, `pdaSeed`, `arbiterChanges`, `houseWager`,
+    // `strictIsolation`, `closeOnSettle`, `scores`, `keeperRewardBps`, `cancelledTime`,
+    // `strictFeeRecipient`, `feeVaultAuthority`, `requireReady`, `player1Ready`,
+    // `player2Ready`, `depositsCompletedTime`, `pairSeed`, `capActiveGames`, `settlementsFrozen`), in an allocation
+    // that much smaller.
+    // `newPlayerWaiver` takes 11 bytes of space, `scores` 9 and `feeVaultAuthority` 33, but
+    // each encodes `None` in 1.
+    const addedBytes = 3 + 11 + 2 + 1 + 8 + 32 + 1 + 1 + 1 + 1 + 9 + 2 + 8 + 1 + 33 + 3 + 8 + 32 + 1 + 1;
+    const addedEncodedBytes = 3 + 1 + 2 + 1 + 8 + 32 + 1 + 1 + 1 + 1 + 1 + 2 + 8 + 1 + 1 + 3 + 8 + 32 + 1 + 1;
+    const account = await context.banksClient.getAccount(wagerPda);
+    const wager = program.coder.accounts.decode("wager", Buffer.from(account.data));
+    // v1 recorded player 1's deposit in its flag rather than a status
+    const encoded = await program.coder.accounts.encode("wager", {
+      ...wager,
+      version: 0,
+      legacyPlayer1Deposited: true,
+      status: { created: {} },
+    });
+    const v1Data = Buffer.alloc(account.data.length - addedBytes);
+    encoded.copy(v1Data, 0, 0, encoded.length - addedEncodedBytes);
+    context.setAccount(wagerPda, { ...account, data: v1Data });
+
+    await migrateWager();
+
+    const migrated = await context.banksClient.getAccount(wagerPda);
+    expect(migrated.data.length).to.equal(account.data.length);
+    const wagerAccount = await program.account.wager.fetch(wagerPda);
+    expect(wagerAccount.version).to.equal(2);
+    expect(wagerAccount.scores).to.be.null;
+    expect(wagerAccount.keeperRewardBps).to.equal(0);
+    expect(wagerAccount.hasSidePool).to.be.false;
+    expect(wagerAccount.newPlayerWaiver).to.be.null;
+    expect(wagerAccount.arbiterFeeBps).to.equal(0);
+    // v1 wagers always rounded the fee down
+    expect(wagerAccount.roundFeeDown).to.be.true;
+    // and get the expiry they would have been created with
+    expect(wagerAccount.expiryTime.toNumber()).to.equal(wagerAccount.creationTime.toNumber() + 60 * 60);
+    // and keep their PDAs derived from player 2
+    expect(wagerAccount.pdaSeed.toString()).to.equal(player2.publicKey.toString());
+    expect(wagerAccount.pairSeed.toString()).to.equal(player2.publicKey.toString());
+    // The status is derived from the deposit flags
+    expect(wagerAccount.status).to.deep.equal({ waitingPlayer2: {} });
+    expect(wagerAccount.player1.toString()).to.equal(player1.publicKey.toString());
+    expect(wagerAccount.wagerAmount.toNumber()).to.equal(wagerAmount.toNumber());
+
+    // The migrated wager keeps working
+    await depositPlayer2();
+    await expectError(migrateWager(), "Wager is already on the current layout version");
+  });
+
+  it("Backfills the payer of a wager created before it was recorded, which then closes to player 1", async () => {
+    await initializeWager();
+    await depositPlayer1();
+    await depositPlayer2();
+    await declareWinner(1);
+
+    // The original layout ended with `initializationCost`: the fixed fields up to it,
+    // with `winner` encoded as `Some` in 2 bytes
+    const originalLength = 8 + 4 * 32 + 8 + 1 + 1 + 8 + 8 + 2 + 1 + 1 + 1 + 8;
+    const account = await context.banksClient.getAccount(wagerPda);
+    context.setAccount(wagerPda, { ...account, data: Buffer.from(account.data).subarray(0, originalLength) });
+
+    await migrateWager();
+    const wagerAccount = await program.account.wager.fetch(wagerPda);
+    expect(wagerAccount.payer.toString()).to.equal(player1.publicKey.toString());
+    expect(wagerAccount.winner).to.equal(1);
+
+    // No pair stats are needed to close it, and the rent goes back to player 1
+    const wagerRent = await getBalance(wagerPda);
+    const vaultRent = Number((await context.banksClient.getRent()).minimumBalance(BigInt(0)));
+    const player1BalanceBefore = await getBalance(player1.publicKey);
+    await program.methods
+      .closeWager()
+      .accounts({
+        wager: wagerPda,
+        vault: vaultPda,
+        pairStats: null,
+        payer: player1.publicKey,
+        feeRecipient: feeRecipient.publicKey,
+      })
+      .rpc();
+
+    expect(await context.banksClient.getAccount(wagerPda)).to.be.null;
+    expect((await getBalance(player1.publicKey)) - player1BalanceBefore).to.equal(wagerRent + vaultRent);
+  });
+
+  describe("claim window", () => {
+    const CLAIM_WINDOW_SECONDS = 300;
+
+    function claimWinnings(winner: Keypair) {
+      return program.methods
+        .claimWinnings()
+        .accounts({
+          wager: wagerPda,
+          vault: vaultPda,
+          player: winner.publicKey,
+          winnerAccount: winner.publicKey,
+        })
+        .signers([winner])
+        .rpc();
+    }
+
+    function sweepUnclaimed(authority: Keypair) {
+      return program.methods
+        .sweepUnclaimed()
+        .accounts({ wager: wagerPda, vault: vaultPda, authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+    }
+
+    async function declareHeld(): Promise<{ declaredTime: number; winnerAmount: number }> {
+      await initializeWager({ claimWindowSeconds: new anchor.BN(CLAIM_WINDOW_SECONDS) });
+      await depositPlayer1();
+      await depositPlayer2();
+
+      const player1BalanceBefore = await getBalance(player1.publicKey);
+      await declareWinner(1);
+
+      // Nothing is paid to the winner at declaration
+      expect(await getBalance(player1.publicKey)).to.equal(player1BalanceBefore);
+      const wagerAccount = await program.account.wager.fetch(wagerPda);
+      return {
+        declaredTime: wagerAccount.declaredTime.toNumber(),
+        winnerAmount: wagerAccount.unclaimedWinnings.toNumber(),
+      };
+    }
+
+    it("Lets the winner claim held winnings within the window", async () => {
+      const { declaredTime, winnerAmount } = await declareHeld();
+      expect(winnerAmount).to.be.greaterThan(0);
+
+      await warpTo(declaredTime + CLAIM_WINDOW_SECONDS);
+      await expectError(sweepUnclaimed(feeRecipient), "Claim window is still open");
+
+      const player1BalanceBefore = await getBalance(player1.publicKey);
+      await claimWinnings(player1);
+
+      // The winner signed and paid the 5000 lamport transaction fee
+      expect((await getBalance(player1.publicKey)) - player1BalanceBefore).to.equal(winnerAmount - 5000);
+      const wagerAccount = await program.account.wager.fetch(wagerPda);
+      expect(wagerAccount.unclaimedWinnings.toNumber()).to.equal(0);
+      await expectError(claimWinnings(player1), "No unclaimed winnings");
+    });
+
+    it("Lets the fee recipient sweep winnings left unclaimed past the deadline", async () => {
+      const { declaredTime, winnerAmount } = await declareHeld();
+      fund(feeRecipient.publicKey, 1 * LAMPORTS_PER_SOL);
+
+      await expectError(sweepUnclaimed(arbiter), "Only the fee recipient or the payer");
+
+      await warpTo(declaredTime + CLAIM_WINDOW_SECONDS + 1);
+      const feeRecipientBalanceBefore = await getBalance(feeRecipient.publicKey);
+      await sweepUnclaimed(feeRecipient);
+
+      expect((await getBalance(feeRecipient.publicKey)) - feeRecipientBalanceBefore).to.equal(winnerAmount - 5000);
+      await expectError(claimWinnings(player1), "No unclaimed winnings");
+    });
+
+    it("Splits held winnings across three team members by share", async () => {
+      const { winnerAmount } = await declareHeld();
+      const members = [Keypair.generate(), Keypair.generate(), Keypair.generate()];
+      const shares = [5000, 3000, 2000];
+
+      function claimAsTeam(shares: number[]) {
+        return program.methods
+          .claimAsTeam(shares)
+          .accounts({ wager: wagerPda, vault: vaultPda, player: player1.publicKey })
+          .remainingAccounts(
+            members.map((member) => ({ pubkey: member.publicKey, isWritable: true, isSigner: false }))
+          )
+          .signers([player1])
+          .rpc();
+      }
+
+      await expectError(claimAsTeam([5000, 3000, 1000]), "Team shares must be positive");
+      await claimAsTeam(shares);
+
+      const received = await Promise.all(members.map((member) => getBalance(member.publicKey)));
+      expect(received[1]).to.equal(Math.floor((winnerAmount * 3000) / 10000));
+      expect(received[2]).to.equal(Math.floor((winnerAmount * 2000) / 10000));
+      // The first member also takes the rounding remainder
+      expect(received[0] + received[1] + received[2]).to.equal(winnerAmount);
+      expect((await program.account.wager.fetch(wagerPda)).unclaimedWinnings.toNumber()).to.equal(0);
+      await expectError(claimWinnings(player1), "No unclaimed winnings");
+    });
+  });
+});
\ No newline at end of file